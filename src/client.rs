@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::config;
+use crate::subscription::{SubjectDetail, Subscription, ThreadID};
+use crate::transport::{Client, HttpTransport};
+use crate::util::{self, Filters};
+
+/// A client for the notifications API, authenticated with a personal access token
+///
+/// Requests hitting the rate limit are retried after `Retry-After`.
+#[derive(Clone, Debug)]
+pub struct GithubNotifications {
+    client: Client,
+}
+
+impl GithubNotifications {
+    pub fn new(token: &str) -> Result<Self> {
+        Ok(Self::from_client(Client::new(HttpTransport::with_token(
+            token,
+        )?)))
+    }
+
+    /// Use the token in `~/.ghnf/token`
+    pub fn from_config() -> Result<Self> {
+        Self::new(&config::read_token()?)
+    }
+
+    /// Use a client set up by the caller, e.g. with a transport replaying fixtures
+    pub const fn from_client(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// The underlying client, for the functions in [`util`] and [`crate::repos`]
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Fetch all unread notifications
+    pub async fn fetch_unread(&self) -> Result<Vec<Subscription>> {
+        Ok(Subscription::fetch_unread(&self.client)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Fetch unread notifications matching `filt`, sorted and truncated as it specifies
+    ///
+    /// `~/.ghnf/ignore` is not applied, see [`util::filter_ignored`].
+    pub async fn fetch(&self, filt: &Filters) -> Result<Vec<Subscription>> {
+        let ss = util::fetch_filtered(filt, &self.client).await?;
+        let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, &self.client).await?;
+        Ok(util::sort_and_take(ss, filt))
+    }
+
+    pub async fn thread(&self, id: ThreadID) -> Result<Subscription> {
+        Subscription::from_thread_id(id, &self.client).await
+    }
+
+    /// The issue, pull request or commit of `n`, fetched once and cached in `n`
+    pub async fn subject_detail<'a>(&self, n: &'a Subscription) -> Result<&'a SubjectDetail> {
+        n.subject_detail(&self.client).await
+    }
+
+    pub async fn unsubscribe(&self, n: &Subscription) -> Result<()> {
+        n.unsubscribe(&self.client).await
+    }
+
+    pub async fn mark_as_read(&self, n: &Subscription) -> Result<()> {
+        n.mark_as_read(&self.client).await
+    }
+
+    /// The url to open `n` at, optionally pointing at the latest comment
+    pub async fn url_to_open(&self, n: &Subscription, latest_comment: bool) -> Result<String> {
+        n.url_to_open(&self.client, latest_comment).await
+    }
+
+    /// Open `n` with `browser` (see [`util::open_url`]), or with the system default
+    pub async fn open(&self, n: &Subscription, browser: Option<&str>) -> Result<()> {
+        util::open_url(&self.url_to_open(n, false).await?, browser)
+    }
+}
@@ -0,0 +1,165 @@
+//! Completion scripts and the man page, generated from `build_app`
+//!
+//! clap only completes what the `App` declares, so thread IDs and `--repo` values (of `read` and `count`) are completed by
+//! hooks calling the hidden `ghnf complete`, which reads `~/.ghnf/recent`.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use clap::{AppSettings, Shell};
+use regex::Regex;
+
+use github_notification_filter::recent;
+
+/// Subcommands taking thread IDs as positional arguments
+const THREAD_ID_SUBCOMMANDS: &str = "open remove rm show";
+
+const BASH_HOOK: &str = r#"
+_ghnf_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}" w
+    if [[ ${prev} == --repo ]] ; then
+        COMPREPLY=( $(compgen -W "$(ghnf complete repos 2>/dev/null)" -- "${cur}") )
+        return 0
+    fi
+    if [[ ${cur} != -* && ${prev} != -* ]] ; then
+        for w in "${COMP_WORDS[@]:1:COMP_CWORD-1}" ; do
+            case " @SUBCOMMANDS@ " in
+                *" ${w} "*)
+                    COMPREPLY=( $(compgen -W "$(ghnf complete thread-ids 2>/dev/null | cut -f1)" -- "${cur}") )
+                    return 0
+                    ;;
+            esac
+        done
+    fi
+    _ghnf "$@"
+}
+
+complete -F _ghnf_dynamic -o bashdefault -o default ghnf
+"#;
+
+const ZSH_HOOK: &str = r#"
+_ghnf_thread_ids() {
+    local -a ids
+    ids=(${${(f)"$(ghnf complete thread-ids 2>/dev/null)"}/$'\t'/:})
+    _describe -t thread-ids 'thread ID' ids
+}
+
+_ghnf_repos() {
+    local -a repos
+    repos=(${(f)"$(ghnf complete repos 2>/dev/null)"})
+    _describe -t repos 'repository' repos
+}
+"#;
+
+const FISH_HOOK: &str = r#"
+complete -c ghnf -n "__fish_seen_subcommand_from @SUBCOMMANDS@" -f -a "(ghnf complete thread-ids 2>/dev/null)"
+complete -c ghnf -n "__fish_seen_subcommand_from read count" -l repo -x -a "(ghnf complete repos 2>/dev/null)"
+"#;
+
+/// The completion script for `shell`, with the hooks
+pub fn script(shell: Shell) -> String {
+    let mut buf = vec![];
+    crate::build_app().gen_completions_to("ghnf", shell, &mut buf);
+    let script = String::from_utf8(buf).expect("completion scripts are UTF-8");
+
+    match shell {
+        Shell::Bash => {
+            // the hook registers itself instead of `_ghnf`
+            let script = script
+                .trim_end()
+                .trim_end_matches("complete -F _ghnf -o bashdefault -o default ghnf");
+            script.to_owned() + &BASH_HOOK.replace("@SUBCOMMANDS@", THREAD_ID_SUBCOMMANDS)
+        }
+        Shell::Zsh => {
+            // match argument names rather than help texts, clap 2 can't attach a completion function
+            let ids = Regex::new(r"(?m)^('::?thread_ids?):_files'").unwrap();
+            let repo = Regex::new(r"(?m)^('--repo=\[.*\])' \\$").unwrap();
+            let script = ids.replace_all(&script, "$1:_ghnf_thread_ids'");
+            let script = repo.replace_all(&script, "$1: :_ghnf_repos' \\");
+            // the helpers have to be defined before `_ghnf "$@"` at the end
+            let (head, tail) = script.split_at(script.find('\n').map_or(0, |i| i + 1));
+            format!("{}{}{}", head, ZSH_HOOK, tail)
+        }
+        _ => script + &FISH_HOOK.replace("@SUBCOMMANDS@", THREAD_ID_SUBCOMMANDS),
+    }
+}
+
+/// Candidates for the hooks: thread IDs (with their titles after a tab) or repositories
+pub fn print_candidates(what: &str) -> Result<()> {
+    let recent = recent::load()?;
+    match what {
+        "thread-ids" => {
+            for r in &recent {
+                outln!("{}\t{} : {}", r.thread_id, r.repo, r.title)?;
+            }
+        }
+        "repos" => {
+            for r in recent::repos(&recent) {
+                outln!("{}", r)?;
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Help of the subcommand at `path` (empty for the top level), as `--help` prints it
+fn help_text(path: &[&str]) -> String {
+    let app = crate::build_app()
+        .setting(AppSettings::ColorNever)
+        .set_term_width(80);
+    let args = std::iter::once("ghnf")
+        .chain(path.iter().copied())
+        .chain(std::iter::once("--help"));
+    match app.get_matches_from_safe(args) {
+        Err(e) if e.kind == clap::ErrorKind::HelpDisplayed => e.message,
+        _ => unreachable!("--help is always accepted"),
+    }
+}
+
+/// Escape text for roff, keeping it as preformatted lines
+fn roff(text: &str) -> String {
+    text.lines()
+        .map(|l| {
+            let l = l.replace('\\', "\\e");
+            if l.starts_with('.') || l.starts_with('\'') {
+                format!("\\&{}", l)
+            } else {
+                l
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The man page in roff
+pub fn man() -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        ".TH GHNF 1 \"\" \"ghnf {}\" \"User Commands\"",
+        clap::crate_version!()
+    )
+    .unwrap();
+    out.push_str(".SH NAME\nghnf \\- filter GitHub notifications\n");
+    out.push_str(".SH SYNOPSIS\n.B ghnf\n[\\fIOPTIONS\\fR] \\fISUBCOMMAND\\fR\n");
+    writeln!(out, ".SH DESCRIPTION\n.nf\n{}\n.fi", roff(&help_text(&[]))).unwrap();
+
+    out.push_str(".SH SUBCOMMANDS\n");
+    for path in crate::SUBCOMMANDS {
+        writeln!(out, ".SS ghnf {}", path.join(" ")).unwrap();
+        writeln!(out, ".nf\n{}\n.fi", roff(&help_text(path))).unwrap();
+    }
+
+    out.push_str(
+        ".SH FILES\n\
+         .TP\n~/.ghnf/token\npersonal access token\n\
+         .TP\n~/.ghnf/filters\nregexes of threads to unsubscribe\n\
+         .TP\n~/.ghnf/ignore\nthread IDs never to unsubscribe\n\
+         .TP\n~/.ghnf/journal\nactions taken, for undo\n\
+         .TP\n~/.ghnf/snoozed\nsnoozed threads\n\
+         .TP\n~/.ghnf/recent\nthreads seen in recent runs, for completion\n\
+         .TP\n~/.ghnf/count\ninbox cached by count, and the threads seen by its last checks\n",
+    );
+    out
+}
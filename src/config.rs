@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use crate::subscription::ThreadID;
+use crate::transport::Client;
+
+/// Severity of a problem found in a config file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a config file, optionally pointing at a line (1-origin)
+///
+/// `file` is the path for display, e.g. `~/.ghnf/filters`
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub file: String,
+    pub line: Option<usize>,
+    pub level: Level,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn error(file: &str, line: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_owned(),
+            line,
+            level: Level::Error,
+            message,
+        }
+    }
+
+    pub fn warning(file: &str, line: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_owned(),
+            line,
+            level: Level::Warning,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(l) = self.line {
+            write!(f, ":{}", l)?;
+        }
+        write!(f, ": {}: {}", self.level, self.message)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ConfigError(pub Problem);
+
+/// GitHub rejected the token (401), reported as an authentication failure rather than a
+/// configuration error
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct TokenRejected(pub Problem);
+
+pub fn config_path(filename: &str) -> Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .ok_or_else(|| anyhow!("Failed to read ~/"))?
+        .join(".ghnf")
+        .join(filename))
+}
+
+/// Replace the home directory with `~` for display
+pub fn display_path(path: &Path) -> String {
+    dirs::home_dir()
+        .and_then(|h| path.strip_prefix(h).ok())
+        .map_or_else(
+            || path.display().to_string(),
+            |p| format!("~/{}", p.display()),
+        )
+}
+
+fn read_error(filename: &str, e: &std::io::Error) -> anyhow::Error {
+    ConfigError(Problem::error(
+        &format!("~/.ghnf/{}", filename),
+        None,
+        format!("failed to read: {}", e),
+    ))
+    .into()
+}
+
+pub fn read_config(filename: &str) -> Result<String> {
+    std::fs::read_to_string(config_path(filename)?).map_err(|e| read_error(filename, &e))
+}
+
+/// Read an optional config file, returning an empty string when it does not exist
+pub fn read_optional_config(filename: &str) -> Result<String> {
+    match std::fs::read_to_string(config_path(filename)?) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        r => r.map_err(|e| read_error(filename, &e)),
+    }
+}
+
+/// Parse the content of `ignore`, skipping (and reporting) malformed lines
+pub fn parse_ignored(text: &str) -> (Vec<(usize, ThreadID)>, Vec<Problem>) {
+    let mut ids = vec![];
+    let mut problems = vec![];
+    for (i, l) in text.split('\n').enumerate() {
+        let l = l.trim();
+        if l.is_empty() {
+            continue;
+        }
+        match l.parse::<ThreadID>() {
+            Ok(id) => ids.push((i + 1, id)),
+            Err(_) => problems.push(Problem::error(
+                "~/.ghnf/ignore",
+                Some(i + 1),
+                format!("malformed thread ID: {:?}", l),
+            )),
+        }
+    }
+    (ids, problems)
+}
+
+pub fn load_ignored() -> Result<Vec<ThreadID>> {
+    // `ignore` is optional, return empty vec when not found
+    let (ids, problems) = parse_ignored(&read_optional_config("ignore")?);
+    for p in problems {
+        tracing::warn!("{} (skipped)", p);
+    }
+    Ok(ids.into_iter().map(|(_, id)| id).collect())
+}
+
+pub fn read_token() -> Result<String> {
+    read_config("token")?
+        .split('\n')
+        .next()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| {
+            ConfigError(Problem::error(
+                "~/.ghnf/token",
+                Some(1),
+                "Malformed GitHub Personal Access Token".to_owned(),
+            ))
+            .into()
+        })
+}
+
+/// Static checks on `ignore`: malformed IDs and duplicates
+pub fn check_ignored(text: &str) -> Vec<Problem> {
+    let (ids, mut problems) = parse_ignored(text);
+    let mut seen = HashMap::new();
+    for (line, id) in ids {
+        if let Some(first) = seen.get(&id) {
+            problems.push(Problem::warning(
+                "~/.ghnf/ignore",
+                Some(line),
+                format!("duplicate of thread {} at line {}", id, first),
+            ));
+        } else {
+            seen.insert(id, line);
+        }
+    }
+    problems
+}
+
+pub struct TokenInfo {
+    pub login: String,
+    pub scopes: Option<String>,
+}
+
+/// Validate the token with a cheap authenticated call
+pub async fn check_token(c: &Client) -> Result<TokenInfo> {
+    #[derive(serde::Deserialize)]
+    struct User {
+        login: String,
+    }
+
+    let resp = c.get("https://api.github.com/user").send().await?;
+    let status = resp.status();
+    let scopes = resp
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok())
+        .map(ToOwned::to_owned);
+    if status == 401 {
+        return Err(TokenRejected(Problem::error(
+            "~/.ghnf/token",
+            Some(1),
+            "GitHub rejected the token (401 Unauthorized)".to_owned(),
+        ))
+        .into());
+    }
+    if status != 200 {
+        return Err(anyhow!(
+            "Unexpected HTTP Status {} while validating the token",
+            status
+        ));
+    }
+    let user: User = serde_json::from_str(&resp.text())?;
+    Ok(TokenInfo {
+        login: user.login,
+        scopes,
+    })
+}
@@ -0,0 +1,194 @@
+//! Unread counts for status bars, cheap enough to poll every minute
+//!
+//! The inbox is cached in `~/.ghnf/count` with its `Last-Modified`, so a poll is a single
+//! conditional request answered with `304 Not Modified` until something changes.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config;
+use crate::subscription::gh_objects::Notification;
+use crate::subscription::{Subscription, ThreadID};
+use crate::transport::Client;
+use crate::util::{self, Filters};
+
+/// Repositories listed in the tooltip
+const TOOLTIP_REPOS: usize = 10;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    last_modified: Option<String>,
+    /// the whole inbox at `last_modified`
+    threads: Vec<Notification>,
+    /// `updated_at` of the matched threads at the last check, per filter
+    seen: HashMap<String, HashMap<ThreadID, String>>,
+}
+
+impl State {
+    fn load() -> Result<Self> {
+        let text = config::read_optional_config("count")?;
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&text).context("Failed to parse ~/.ghnf/count")
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = config::config_path("count")?;
+        std::fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", config::display_path(&path)))
+    }
+}
+
+/// Result of `check`
+#[derive(Debug)]
+pub struct Counts {
+    pub unread: usize,
+    /// threads which are new or updated since the last check with the same filter
+    pub new: usize,
+    /// the most notifying repositories first
+    pub by_repo: Vec<(String, usize)>,
+}
+
+/// Count the unread threads matched by `filt`
+///
+/// `key` tells filters apart, so that checks with different filters (e.g. one per status bar
+/// module) each have their own "new since the last check". `filt.state` (other than `any`) and
+/// `filt.detail` cost a request per thread on every check.
+pub async fn check(c: &Client, filt: &Filters, key: &str) -> Result<Counts> {
+    let mut state = State::load().unwrap_or_else(|e| {
+        tracing::warn!("Ignoring ~/.ghnf/count: {:#}", e);
+        State::default()
+    });
+
+    let polled = Subscription::poll_unread(c, state.last_modified.as_deref()).await?;
+    if let Some(ss) = polled.subscriptions {
+        state.threads = ss.iter().map(Notification::from).collect();
+        state.last_modified = polled.last_modified;
+    } else {
+        tracing::debug!("Inbox not modified, counting the threads in ~/.ghnf/count");
+    }
+
+    let ss = state
+        .threads
+        .iter()
+        .cloned()
+        .map(Subscription::try_from)
+        .collect::<Result<_>>()?;
+    let ss = util::apply_filters(ss, filt, c).await?;
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+
+    let seen = state.seen.get(key);
+    let new = ss
+        .iter()
+        .filter(|s| seen.is_none_or(|seen| seen.get(&s.thread_id) != Some(&s.updated_at)))
+        .count();
+    let mut by_repo: Vec<(String, usize)> = util::group_by(&ss, util::GroupKey::Repo)
+        .into_iter()
+        .map(|(repo, group)| (repo, group.len()))
+        .collect();
+    by_repo.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    state.seen.insert(
+        key.to_owned(),
+        ss.iter()
+            .map(|s| (s.thread_id, s.updated_at.clone()))
+            .collect(),
+    );
+    state.save()?;
+
+    Ok(Counts {
+        unread: ss.len(),
+        new,
+        by_repo,
+    })
+}
+
+/// Output formats of `Counts::render`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `12 (+3)`
+    Plain,
+    /// JSON for a waybar custom module, with `class` of `empty`, `unread` or `new`
+    Waybar,
+    /// JSON for i3blocks with `format=json`, urgent when something is new
+    I3blocks,
+    /// bold in the tmux status line when something is new
+    Tmux,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "waybar" => Ok(Self::Waybar),
+            "i3blocks" => Ok(Self::I3blocks),
+            "tmux" => Ok(Self::Tmux),
+            _ => Err(format!("unknown format {:?}", s)),
+        }
+    }
+}
+
+impl Counts {
+    fn text(&self) -> String {
+        if self.new > 0 {
+            format!("{} (+{})", self.unread, self.new)
+        } else {
+            self.unread.to_string()
+        }
+    }
+
+    fn tooltip(&self) -> String {
+        std::iter::once(format!("{} unread, {} new", self.unread, self.new))
+            .chain(
+                self.by_repo
+                    .iter()
+                    .take(TOOLTIP_REPOS)
+                    .map(|(repo, n)| format!("{:>4}  {}", n, repo)),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A single line (without the line break) in `format`
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Plain => self.text(),
+            Format::Waybar => {
+                let class = if self.new > 0 {
+                    "new"
+                } else if self.unread > 0 {
+                    "unread"
+                } else {
+                    "empty"
+                };
+                json!({
+                    "text": self.text(),
+                    "tooltip": self.tooltip(),
+                    "class": class,
+                    "alt": class,
+                })
+                .to_string()
+            }
+            Format::I3blocks => json!({
+                "full_text": self.text(),
+                "short_text": self.unread.to_string(),
+                "urgent": self.new > 0,
+            })
+            .to_string(),
+            Format::Tmux => {
+                if self.new > 0 {
+                    format!("#[bold]{}#[nobold]", self.text())
+                } else {
+                    self.text()
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Daily digest of the inbox, as Markdown or HTML for standup notes
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{html, Parser};
+
+use crate::journal::{self, Action};
+use crate::subscription::Subscription;
+use crate::transport::Client;
+use crate::SubjectType;
+
+/// A thread updated in the window
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub title: String,
+    pub repo: String,
+    pub kind: SubjectType,
+    pub reason: String,
+    /// as `stats` labels it: `open`, `closed`, `merged`, or `-`
+    pub state: String,
+    pub link: String,
+}
+
+/// A thread unsubscribed by a rule in the window (and not resubscribed since)
+#[derive(Clone, Debug)]
+pub struct Unsubscribed {
+    pub title: String,
+    pub repo: String,
+    pub rule: String,
+}
+
+#[derive(Debug)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub items: Vec<Item>,
+    pub unsubscribed: Vec<Unsubscribed>,
+}
+
+/// Collect threads of `ss` updated since `since`, with a request per thread for its state and
+/// page, and the auto-unsubscribed threads from `journal`
+pub async fn collect(
+    ss: &[Subscription],
+    journal: &[journal::Entry],
+    since: DateTime<Utc>,
+    c: &Client,
+) -> Result<Digest> {
+    let ss: Vec<&Subscription> = ss
+        .iter()
+        .filter(|s| DateTime::parse_from_rfc3339(&s.updated_at).is_ok_and(|t| t >= since))
+        .collect();
+
+    let mut items = Vec::with_capacity(ss.len());
+    for chunk in ss.chunks(crate::util::CHUNK_SIZE) {
+        let futs = chunk.iter().map(|s| async move {
+            // the detail is fetched once for both
+            let state = crate::stats::state_label(s, c).await;
+            let link = s.url_to_open(c, false).await;
+            match (state, link) {
+                (Ok(state), Ok(link)) => (state, link),
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::warn!("Failed to get the detail of {}: {:#}", s, e);
+                    (
+                        "-".to_owned(),
+                        format!("https://github.com/{}", s.repo_name),
+                    )
+                }
+            }
+        });
+        let details = futures::future::join_all(futs).await;
+        items.extend(chunk.iter().zip(details).map(|(s, (state, link))| Item {
+            title: s.subject.title.clone(),
+            repo: s.repo_name.clone(),
+            kind: s.subject.r#type,
+            reason: s.reason.clone(),
+            state,
+            link,
+        }));
+    }
+
+    let unsubscribed = journal::undoable(journal, Some(since))
+        .into_iter()
+        .filter(|e| e.action == Action::Unsubscribe)
+        .filter_map(|e| {
+            Some(Unsubscribed {
+                title: e.title.clone(),
+                repo: e.repo.clone(),
+                rule: e.rule.clone()?,
+            })
+        })
+        .collect();
+
+    Ok(Digest {
+        since,
+        items,
+        unsubscribed,
+    })
+}
+
+/// Escape characters with a meaning in Markdown inline text
+fn escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\`*_[]<>#|~!".contains(c) {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+fn badge(state: &str) -> &'static str {
+    match state {
+        "open" => "🟢 open ",
+        "merged" => "🟣 merged ",
+        "closed" => "🔴 closed ",
+        _ => "",
+    }
+}
+
+const fn kind_name(kind: SubjectType) -> &'static str {
+    match kind {
+        SubjectType::Commit => "commit",
+        SubjectType::Discussion => "discussion",
+        SubjectType::Issue => "issue",
+        SubjectType::PullRequest => "pull request",
+    }
+}
+
+/// Group by `key`, the largest groups first
+fn group<'a, T>(xs: &'a [T], key: impl Fn(&'a T) -> &'a str) -> Vec<(&'a str, Vec<&'a T>)> {
+    let mut groups = BTreeMap::<&str, Vec<&T>>::new();
+    for x in xs {
+        groups.entry(key(x)).or_default().push(x);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    groups
+}
+
+impl Digest {
+    /// Sections per repository and reason, then the auto-unsubscribed threads
+    pub fn markdown(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "# GitHub notifications since {}",
+                self.since.format("%Y-%m-%d %H:%M UTC")
+            ),
+            String::new(),
+        ];
+
+        let repos = group(&self.items, |i| &i.repo);
+        let reasons = group(&self.items, |i| &i.reason)
+            .iter()
+            .map(|(r, is)| format!("{} {}", is.len(), escape(r)))
+            .collect::<Vec<_>>();
+        let reasons = if reasons.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", reasons.join(", "))
+        };
+        lines.push(format!(
+            "{} updated thread(s) in {} repository(ies){}. {} thread(s) auto-unsubscribed.",
+            self.items.len(),
+            repos.len(),
+            reasons,
+            self.unsubscribed.len()
+        ));
+
+        for (repo, items) in repos {
+            lines.push(String::new());
+            lines.push(format!("## {} ({})", escape(repo), items.len()));
+            for (reason, items) in group(&items, |i| &i.reason) {
+                lines.push(String::new());
+                lines.push(format!("### {} ({})", escape(reason), items.len()));
+                lines.push(String::new());
+                for i in items {
+                    lines.push(format!(
+                        "- {}[{}]({}) ({})",
+                        badge(&i.state),
+                        escape(&i.title),
+                        i.link,
+                        kind_name(i.kind)
+                    ));
+                }
+            }
+        }
+
+        if !self.unsubscribed.is_empty() {
+            lines.push(String::new());
+            lines.push(format!(
+                "## Auto-unsubscribed ({})",
+                self.unsubscribed.len()
+            ));
+            lines.push(String::new());
+            for u in &self.unsubscribed {
+                lines.push(format!(
+                    "- {}: {} (rule: `{}`)",
+                    escape(&u.repo),
+                    escape(&u.title),
+                    u.rule.replace('`', "'")
+                ));
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// `markdown` rendered as a standalone HTML document
+    pub fn html(&self) -> String {
+        let mut body = String::new();
+        html::push_html(&mut body, Parser::new(&self.markdown()));
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>GitHub notifications</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+            body
+        )
+    }
+}
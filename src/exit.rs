@@ -0,0 +1,73 @@
+//! Exit codes of `ghnf`, documented in the README
+
+use github_notification_filter::config::{ConfigError, TokenRejected};
+use github_notification_filter::subscription::UnexpectedStatus;
+use github_notification_filter::util::PartialFailure;
+
+/// Nothing matched the filters (or the arguments), which is not an error by itself
+#[derive(Debug, thiserror::Error)]
+#[error("nothing matched")]
+pub struct NoMatches;
+
+/// Exit codes of failed runs, a run which did something (or matched something) exits with 0
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// any other error, including invalid arguments
+    Error = 1,
+    NoMatches = 2,
+    /// some threads (or repositories) failed while the others were handled
+    PartialFailure = 3,
+    /// GitHub rejected the token, also by `config check`
+    AuthFailure = 4,
+    /// the rate limit is exhausted until `X-RateLimit-Reset`
+    RateLimited = 5,
+    /// a file under `~/.ghnf` is missing or malformed
+    ConfigError = 6,
+}
+
+impl ExitCode {
+    pub fn of(e: &anyhow::Error) -> Self {
+        for cause in e.chain() {
+            if cause.is::<NoMatches>() {
+                return Self::NoMatches;
+            }
+            if cause.is::<PartialFailure>() {
+                return Self::PartialFailure;
+            }
+            if cause.is::<TokenRejected>() {
+                return Self::AuthFailure;
+            }
+            if let Some(u) = cause.downcast_ref::<UnexpectedStatus>() {
+                if u.is_auth_failure() {
+                    return Self::AuthFailure;
+                }
+                if u.rate_limited {
+                    return Self::RateLimited;
+                }
+            }
+            if cause.is::<ConfigError>() {
+                return Self::ConfigError;
+            }
+        }
+        Self::Error
+    }
+}
+
+/// Print `e` with its causes (unless nothing matched, which commands report themselves) and exit
+pub fn exit_with(e: &anyhow::Error) -> ! {
+    // the reader has gone, e.g. `ghnf man | head`, which is fine
+    if e.chain()
+        .filter_map(|c| c.downcast_ref::<std::io::Error>())
+        .any(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+    {
+        std::process::exit(0)
+    }
+    let code = ExitCode::of(e);
+    if code != ExitCode::NoMatches {
+        eprintln!("Error: {}", e);
+        for cause in e.chain().skip(1) {
+            eprintln!("  caused by: {}", cause);
+        }
+    }
+    std::process::exit(code as i32)
+}
@@ -0,0 +1,98 @@
+//! Atom feed of notifications, for triaging in a feed reader
+
+use anyhow::Result;
+
+use crate::subscription::{Subscription, ThreadID};
+use crate::transport::Client;
+use crate::SubjectType;
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub thread_id: ThreadID,
+    pub title: String,
+    pub repo: String,
+    pub kind: SubjectType,
+    pub reason: String,
+    pub updated_at: String,
+    /// the issue, pull request, commit or discussions page on github.com
+    pub link: String,
+}
+
+/// Entries of `ss`, with a request per thread for its page (`CHUNK_SIZE` at once)
+pub async fn entries(ss: &[Subscription], c: &Client) -> Result<Vec<Entry>> {
+    let mut entries = Vec::with_capacity(ss.len());
+    for chunk in ss.chunks(crate::util::CHUNK_SIZE) {
+        let links = futures::future::join_all(chunk.iter().map(|s| s.url_to_open(c, false))).await;
+        for (s, link) in chunk.iter().zip(links) {
+            let link = link.unwrap_or_else(|e| {
+                // e.g. the issue was deleted, the repository is the best we have
+                tracing::warn!("Failed to get the page of {}: {:#}", s, e);
+                format!("https://github.com/{}", s.repo_name)
+            });
+            entries.push(Entry {
+                thread_id: s.thread_id,
+                title: s.subject.title.clone(),
+                repo: s.repo_name.clone(),
+                kind: s.subject.r#type,
+                reason: s.reason.clone(),
+                updated_at: s.updated_at.clone(),
+                link,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An Atom document of `entries`, updated at the latest entry (or `now` when empty)
+pub fn atom(entries: &[Entry], now: &str) -> String {
+    let updated = entries
+        .iter()
+        .map(|e| e.updated_at.as_str())
+        .max()
+        .unwrap_or(now);
+
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="utf-8"?>"#.to_owned(),
+        r#"<feed xmlns="http://www.w3.org/2005/Atom">"#.to_owned(),
+        "  <title>GitHub notifications</title>".to_owned(),
+        "  <id>https://api.github.com/notifications</id>".to_owned(),
+        r#"  <link href="https://github.com/notifications"/>"#.to_owned(),
+        format!("  <updated>{}</updated>", escape(updated)),
+        "  <author><name>GitHub</name></author>".to_owned(),
+        format!(
+            r#"  <generator version="{}">ghnf</generator>"#,
+            env!("CARGO_PKG_VERSION")
+        ),
+    ];
+    for e in entries {
+        lines.extend([
+            "  <entry>".to_owned(),
+            format!("    <title>{}</title>", escape(&e.title)),
+            // stable per thread, so that readers show an updated thread as updated
+            format!(
+                "    <id>https://api.github.com/notifications/threads/{}</id>",
+                e.thread_id
+            ),
+            format!(r#"    <link href="{}"/>"#, escape(&e.link)),
+            format!("    <updated>{}</updated>", escape(&e.updated_at)),
+            format!(r#"    <category term="{}"/>"#, escape(&e.repo)),
+            format!(r#"    <category term="{}"/>"#, escape(&e.reason)),
+            format!(
+                "    <summary>{} {} ({})</summary>",
+                escape(&e.repo),
+                e.kind,
+                escape(&e.reason)
+            ),
+            "  </entry>".to_owned(),
+        ]);
+    }
+    lines.push("</feed>".to_owned());
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::subscription::{Subscription, ThreadID};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// unsubscribed and marked as read
+    Unsubscribe,
+    MarkRead,
+    Resubscribe,
+    UnwatchRepo,
+    IgnoreRepo,
+    WatchRepo,
+    UnignoreRepo,
+}
+
+impl Action {
+    /// the action undoing this one, if it can be undone
+    pub const fn inverse(self) -> Option<Self> {
+        match self {
+            Self::Unsubscribe => Some(Self::Resubscribe),
+            Self::UnwatchRepo => Some(Self::WatchRepo),
+            Self::IgnoreRepo => Some(Self::UnignoreRepo),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsubscribe => write!(f, "unsubscribe"),
+            Self::MarkRead => write!(f, "mark-read"),
+            Self::Resubscribe => write!(f, "resubscribe"),
+            Self::UnwatchRepo => write!(f, "unwatch-repo"),
+            Self::IgnoreRepo => write!(f, "ignore-repo"),
+            Self::WatchRepo => write!(f, "watch-repo"),
+            Self::UnignoreRepo => write!(f, "unignore-repo"),
+        }
+    }
+}
+
+/// A line of `~/.ghnf/journal`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: DateTime<Utc>,
+    /// start time of the invocation, grouping the entries of a run
+    pub run: DateTime<Utc>,
+    /// `None` for actions on a whole repository
+    pub thread_id: Option<ThreadID>,
+    pub repo: String,
+    pub title: String,
+    pub action: Action,
+    /// the rule which selected the thread, if any
+    pub rule: Option<String>,
+}
+
+/// Appends entries to `~/.ghnf/journal`, one JSON object per line
+pub struct Journal {
+    run: DateTime<Utc>,
+    file: Mutex<std::fs::File>,
+}
+
+impl Journal {
+    pub fn open() -> Result<Self> {
+        let path = config::config_path("journal")?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", config::display_path(&path)))?;
+        Ok(Self {
+            run: Utc::now(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, s: &Subscription, action: Action, rule: Option<String>) -> Result<()> {
+        self.write(&Entry {
+            timestamp: Utc::now(),
+            run: self.run,
+            thread_id: Some(s.thread_id),
+            repo: s.repo_name.clone(),
+            title: s.subject.title.clone(),
+            action,
+            rule,
+        })
+    }
+
+    pub fn record_repo(&self, repo: &str, action: Action, rule: Option<String>) -> Result<()> {
+        self.write(&Entry {
+            timestamp: Utc::now(),
+            run: self.run,
+            thread_id: None,
+            repo: repo.to_owned(),
+            title: String::new(),
+            action,
+            rule,
+        })
+    }
+
+    pub fn write(&self, e: &Entry) -> Result<()> {
+        let mut line = serde_json::to_string(e)?;
+        line.push('\n');
+        // a single write per line keeps lines intact with O_APPEND
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .context("Failed to write to ~/.ghnf/journal")
+    }
+
+    pub const fn run(&self) -> DateTime<Utc> {
+        self.run
+    }
+}
+
+/// Read all entries from `~/.ghnf/journal`, skipping (and reporting) malformed lines
+pub fn read_all() -> Result<Vec<Entry>> {
+    let text = config::read_optional_config("journal")?;
+    let mut ret = vec![];
+    for (i, l) in text.lines().enumerate() {
+        if l.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(l) {
+            Ok(e) => ret.push(e),
+            Err(e) => tracing::warn!("~/.ghnf/journal:{}: {} (skipped)", i + 1, e),
+        }
+    }
+    Ok(ret)
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.thread_id {
+            Some(id) => write!(f, "{} : {} ({})", self.repo, self.title, id),
+            None => write!(f, "{} (repository)", self.repo),
+        }
+    }
+}
+
+/// Entries to be undone: the last run which did anything undoable, or since the time
+///
+/// Entries already undone (or repeated) afterwards are excluded.
+pub fn undoable(entries: &[Entry], since: Option<DateTime<Utc>>) -> Vec<&Entry> {
+    // walk backwards once, remembering what later entries did to each target
+    let mut later = HashSet::new();
+    let mut pending: Vec<&Entry> = vec![];
+    for u in entries.iter().rev() {
+        // the same thread, or the same repository
+        let target = (u.thread_id, u.repo.as_str());
+        if let Some(inv) = u.action.inverse() {
+            if !later.contains(&(target, inv)) && !later.contains(&(target, u.action)) {
+                pending.push(u);
+            }
+        }
+        later.insert((target, u.action));
+    }
+    pending.reverse();
+
+    if let Some(t) = since {
+        pending.into_iter().filter(|e| e.timestamp >= t).collect()
+    } else {
+        let last_run = pending.last().map(|e| e.run);
+        pending
+            .into_iter()
+            .filter(|e| Some(e.run) == last_run)
+            .collect()
+    }
+}
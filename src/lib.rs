@@ -0,0 +1,64 @@
+//! Filter GitHub notifications: fetch the inbox, match threads against rules and the
+//! issue/pull request detail, and unsubscribe from, mark as read or open them.
+//!
+//! The `ghnf` command line tool is built on this crate. Start from [`GithubNotifications`]:
+//!
+//! ```no_run
+//! # async fn f() -> anyhow::Result<()> {
+//! use github_notification_filter::{Filters, GithubNotifications, StateFilter};
+//!
+//! let gh = GithubNotifications::from_config()?; // ~/.ghnf/token
+//! let filt = Filters {
+//!     rules: Some(github_notification_filter::rules::load_rules()?),
+//!     state: StateFilter::Closed,
+//!     ..Filters::default()
+//! };
+//! for n in gh.fetch(&filt).await? {
+//!     gh.unsubscribe(&n).await?;
+//!     gh.mark_as_read(&n).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Nothing here prints to stdout or exits the process. Diagnostics (progress, waiting for the
+//! rate limit) are emitted as [`tracing`] events.
+
+#![warn(future_incompatible)]
+#![warn(rust_2018_compatibility)]
+#![warn(rust_2018_idioms)]
+#![warn(clippy::nursery)]
+#![warn(clippy::pedantic)]
+#![allow(clippy::fallible_impl_from)]
+#![allow(clippy::future_not_send)]
+#![allow(clippy::match_wildcard_for_single_variants)]
+#![allow(clippy::uninlined_format_args)]
+#![allow(clippy::iter_with_drain)]
+#![allow(clippy::too_many_lines)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::must_use_candidate)]
+
+mod client;
+pub mod config;
+pub mod count;
+pub mod digest;
+pub mod feed;
+pub mod journal;
+pub mod markdown;
+pub mod plan;
+pub mod recent;
+pub mod repos;
+pub mod rules;
+pub mod snooze;
+pub mod stats;
+pub mod subscription;
+pub mod suggest;
+pub mod transport;
+pub mod util;
+
+pub use client::GithubNotifications;
+pub use subscription::gh_objects::SubjectType;
+pub use subscription::{SubjectDetail, SubjectState, Subscription, ThreadID};
+pub use transport::Client;
+pub use util::{CommitPolicy, DetailFilter, Filters, Sort, SortKey, StateFilter};
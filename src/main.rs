@@ -6,18 +6,41 @@
 #![allow(clippy::fallible_impl_from)]
 #![allow(clippy::future_not_send)]
 #![allow(clippy::match_wildcard_for_single_variants)]
+#![allow(clippy::uninlined_format_args)]
+#![allow(clippy::iter_with_drain)]
+#![allow(clippy::too_many_lines)]
 
-use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{crate_version, App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
 use futures::future;
-use reqwest::Client;
 
-mod subscription;
-mod util;
+/// `println!` which fails instead of panicking when stdout is closed (`ghnf list | head`)
+macro_rules! outln {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        writeln!(std::io::stdout(), $($arg)*)
+    }};
+}
+
+/// `print!` which fails instead of panicking when stdout is closed
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        write!(std::io::stdout(), $($arg)*)
+    }};
+}
 
-use crate::subscription::gh_objects::SubjectType;
-use crate::subscription::Subscription;
-use util::Filters;
+mod completion;
+mod exit;
+mod watch;
+
+use github_notification_filter::{
+    config, count, digest, feed, journal, markdown, plan, recent, repos, rules, snooze, stats,
+    suggest, util,
+};
+use github_notification_filter::{
+    Client, DetailFilter, Filters, GithubNotifications, Sort, StateFilter, Subscription,
+};
 
 async fn parse_thread_ids(vals: clap::Values<'_>, c: &Client) -> Result<Vec<Subscription>> {
     let mut ids = vec![];
@@ -36,93 +59,1170 @@ async fn parse_thread_ids(vals: clap::Values<'_>, c: &Client) -> Result<Vec<Subs
     Ok(ids)
 }
 
+/// The threads given as `thread_ids`, or the inbox, narrowed by `filt` except for the state
+///
+/// Given threads go through the same filters as the inbox, though `~/.ghnf/filters` is only
+/// loaded without them (see `parse_filters`).
+async fn fetch_selected(
+    m: &ArgMatches<'_>,
+    filt: &Filters,
+    c: &Client,
+) -> Result<Vec<Subscription>> {
+    if let Some(i) = m.values_of("thread_ids") {
+        let ss = parse_thread_ids(i, c).await?;
+        util::apply_filters(ss, filt, c).await
+    } else {
+        util::fetch_filtered(filt, c)
+            .await
+            .inspect(|ss| remember(ss))
+    }
+}
+
+/// `--browser`, or the first line of `~/.ghnf/browser`
+fn browser_command(m: &ArgMatches<'_>) -> Result<Option<String>> {
+    Ok(match m.value_of("browser") {
+        Some(b) => Some(b.to_owned()),
+        None => config::read_optional_config("browser")?
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(ToOwned::to_owned),
+    })
+}
+
 async fn sc_open(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
-    let ss = {
-        if let Some(i) = m.values_of("thread_ids") {
-            parse_thread_ids(i, c).await
-        } else {
-            util::fetch_filtered(Filters::new(m, false)?, c).await
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = fetch_selected(m, &filt, c).await?;
+
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+    let ss = util::sort_and_take(ss, &filt);
+
+    if ss.is_empty() {
+        outln!("No notification matched")?;
+        return Err(exit::NoMatches.into());
+    }
+
+    let latest_comment = m.is_present("latest-comment");
+
+    if m.is_present("print-url") {
+        let urls =
+            future::try_join_all(ss.iter().map(|s| s.url_to_open(c, latest_comment))).await?;
+        for u in urls {
+            outln!("{}", u)?;
         }
-    }?;
+        return Ok(());
+    }
+
+    let browser = browser_command(m)?;
+    let browser = browser.as_deref();
+    let mark_read = m.is_present("mark-read");
+    let max_tabs =
+        match m.value_of("max-tabs") {
+            Some(v) => v.parse().ok().filter(|&n| n > 0).ok_or_else(|| {
+                anyhow!("Invalid argument for <max-tabs>, expected positive integer")
+            })?,
+            None => usize::MAX,
+        };
+    let delay: u64 = m
+        .value_of("batch-delay")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Invalid argument for <batch-delay>, expected integer"))?;
+
+    tracing::info!("Opening {} page(s)...", ss.len());
+
+    for (i, chunk) in ss.chunks(max_tabs).enumerate() {
+        if i > 0 {
+            tracing::info!("Waiting {} sec(s) before the next batch...", delay);
+            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+        }
+        let futs = chunk.iter().map(|s| async move {
+            let url = s.url_to_open(c, latest_comment).await?;
+            outln!("Open {}", s)?;
+            util::open_url(&url, browser)?;
+            if mark_read {
+                s.mark_as_read(c).await?;
+            }
+            Result::<()>::Ok(())
+        });
+        future::try_join_all(futs).await?;
+    }
 
-    let ss = if m.is_present("closed") {
-        util::filter_by_subject_state(ss, subscription::SubjectState::Closed, c).await?
+    Ok(())
+}
+
+async fn sc_list(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = util::fetch_filtered(&filt, c).await?;
+    remember(&ss);
+    let snoozed = snooze::load()?;
+    let now = chrono::Utc::now();
+    let ss = snooze::filter_snoozed(ss, &snoozed, now);
+
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+    let ss = util::sort_and_take(ss, &filt);
+
+    if let Some(key) = m.value_of("group-by") {
+        for (k, group) in util::group_by(&ss, key.parse().unwrap()) {
+            outln!("\n== {} ({}) ==", k, group.len())?;
+            for s in group {
+                outln!("{}", s)?;
+            }
+        }
+        outln!()?;
     } else {
-        ss
+        for s in &ss {
+            outln!("{}", s)?;
+        }
+    }
+    outln!("Total entry count: {}", ss.len())?;
+
+    let due = snoozed.iter().filter(|e| e.is_due(now)).count();
+    if due > 0 {
+        outln!("{} snoozed thread(s) due, see `ghnf snooze wake`", due)?;
+    }
+
+    if ss.is_empty() {
+        return Err(exit::NoMatches.into());
+    }
+    Ok(())
+}
+
+/// Keep fetched threads in ~/.ghnf/recent for shell completion, which is best effort
+fn remember(ss: &[Subscription]) {
+    if let Err(e) = recent::save(ss) {
+        tracing::warn!("Failed to update ~/.ghnf/recent: {:#}", e);
+    }
+}
+
+/// Select threads to be unsubscribed, shared by `remove` and `plan`
+async fn select_removal(
+    m: &ArgMatches<'_>,
+    filt: &Filters,
+    c: &Client,
+) -> Result<Vec<Subscription>> {
+    let ss = fetch_selected(m, filt, c).await?;
+    tracing::info!("{} notifications left", ss.len());
+
+    tracing::info!("Filtering notifications by state ({})...", filt.state);
+    let ss: Vec<Subscription> =
+        util::filter_by_subject_state(util::filter_ignored(ss)?, filt.state, filt.commits, c)
+            .await?;
+    let ss = util::sort_and_take(ss, filt);
+    tracing::info!("{} notification(s) left", ss.len());
+    Ok(ss)
+}
+
+async fn sc_remove(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let dry = m.is_present("dry-run");
+
+    let filt = parse_filters(m, !m.is_present("thread_ids"), StateFilter::Closed)?;
+    let ss = select_removal(m, &filt, c).await?;
+
+    if ss.is_empty() {
+        outln!("No notification matched")?;
+        return Err(exit::NoMatches.into());
+    }
+
+    if dry {
+        outln!("Following threads are going to be unsubscribed:")?;
+        for s in &ss {
+            outln!("{}", s)?;
+        }
+        return Ok(());
+    }
+
+    tracing::info!("Unsubscribing notifications...");
+    util::unsubscribe_all(ss, &filt, c).await
+}
+
+async fn sc_plan(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let filt = parse_filters(m, !m.is_present("thread_ids"), StateFilter::Closed)?;
+    let ss = select_removal(m, &filt, c).await?;
+
+    let action = match m.value_of("action") {
+        Some("read") => journal::Action::MarkRead,
+        _ => journal::Action::Unsubscribe,
     };
+    let plan = plan::Plan::new(&ss, action, &filt, std::env::args().collect());
+    for t in &plan.threads {
+        outln!("{} {} : {} ({})", t.action, t.repo, t.title, t.thread_id)?;
+    }
+    let out = m.value_of("output").unwrap();
+    plan.write(out)?;
+    outln!(
+        "Wrote a plan for {} thread(s) to {}",
+        plan.threads.len(),
+        out
+    )?;
+    if plan.threads.is_empty() {
+        return Err(exit::NoMatches.into());
+    }
+    Ok(())
+}
 
-    println!("Opening {} page(s)...", ss.len());
+async fn sc_apply(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let plan = plan::Plan::read(m.value_of("PLAN").unwrap())?;
+    outln!(
+        "Applying a plan for {} thread(s) created at {}",
+        plan.threads.len(),
+        plan.created_at
+    )?;
 
-    let futs = ss.into_iter().map(|s| async move {
-        println!("Open {}", s);
-        s.open(c).await
-    });
+    let dry = m.is_present("dry-run");
+    let journal = if dry {
+        None
+    } else {
+        Some(journal::Journal::open()?)
+    };
+    let (mut applied, mut skipped) = (0, 0);
+    let mut errors = vec![];
+    for t in &plan.threads {
+        let outcome = match plan::apply_thread(t, journal.as_ref(), c).await {
+            Ok(o) => o,
+            Err(e) => {
+                errors.push(e.context(format!("Failed to apply to thread {}", t.thread_id)));
+                continue;
+            }
+        };
+        match outcome {
+            plan::Outcome::Skipped(s) => {
+                outln!(
+                    "Skipped {} (updated at {}, planned at {})",
+                    s,
+                    s.updated_at,
+                    t.updated_at
+                )?;
+                skipped += 1;
+            }
+            plan::Outcome::Applied(s) => {
+                if dry {
+                    outln!("Would {} {}", t.action, s)?;
+                } else {
+                    outln!("{} {}", t.action, s)?;
+                }
+                applied += 1;
+            }
+        }
+    }
+
+    outln!(
+        "{} thread(s) {}, {} skipped",
+        applied,
+        if dry { "to be applied" } else { "applied" },
+        skipped
+    )?;
+    util::partial_result(errors, plan.threads.len())
+}
+
+async fn sc_show(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    use std::io::IsTerminal;
 
-    future::try_join_all(futs).await?;
+    let s = parse_thread_ids(m.values_of("thread_id").unwrap(), c)
+        .await?
+        .remove(0);
 
+    outln!("Title     : {}", s.subject.title)?;
+    outln!("Repository: {}", s.repo_name)?;
+    outln!("Type      : {}", s.subject.r#type)?;
+    outln!("Reason    : {}", s.reason)?;
+    // Discussions don't have detail (until GitHub fixes FIXME!)
+    if s.subject.url.is_some() {
+        let d = s.subject_detail(c).await?;
+        if let Some(state) = d.state {
+            let mut state = format!("{:?}", state).to_lowercase();
+            if d.merged == Some(true) {
+                state.push_str(" (merged)");
+            } else if d.draft == Some(true) {
+                state.push_str(" (draft)");
+            } else if d.is_not_planned() {
+                state.push_str(" (not planned)");
+            }
+            outln!("State     : {}", state)?;
+        }
+        if let Some(u) = &d.user {
+            outln!("Author    : {}", u.login)?;
+        }
+        if !d.labels.is_empty() {
+            let labels: Vec<&str> = d.labels.iter().map(|l| l.name.as_str()).collect();
+            outln!("Labels    : {}", labels.join(", "))?;
+        }
+        if let Some(t) = &d.created_at {
+            outln!("Created   : {}", t)?;
+        }
+        if let Some(t) = &d.closed_at {
+            outln!("Closed    : {}", t)?;
+        }
+    }
+    outln!("Updated   : {}", s.updated_at)?;
+    outln!(
+        "Last read : {}",
+        s.last_read_at.as_deref().unwrap_or("never")
+    )?;
+    if s.subject.url.is_some() {
+        outln!("URL       : {}", s.html_url(c).await?)?;
+    }
+
+    if let Some(comment) = s.latest_comment(c).await? {
+        outln!(
+            "\nLatest comment by {} at {} ({})\n",
+            comment
+                .user
+                .as_ref()
+                .map_or("<unknown>", |u| u.login.as_str()),
+            comment.created_at.as_deref().unwrap_or("<unknown>"),
+            comment.html_url
+        )?;
+        outln!(
+            "{}",
+            markdown::render(
+                comment.body.as_deref().unwrap_or(""),
+                std::io::stdout().is_terminal()
+            )
+        )?;
+    }
     Ok(())
 }
 
-async fn sc_list(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
-    let ss = util::fetch_filtered(Filters::new(m, false)?, c).await?;
+async fn sc_stats(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = util::fetch_filtered(&filt, c).await?;
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+
+    let top = m
+        .value_of("top")
+        .unwrap()
+        .parse()
+        .map_err(|_| anyhow!("Invalid argument for <top>, expected integer"))?;
+    let stats = stats::collect(&ss, m.is_present("with-state"), top, c).await?;
 
-    let ss = if m.is_present("closed") {
-        util::filter_by_subject_state(ss, subscription::SubjectState::Closed, c).await?
+    if m.value_of("format") == Some("json") {
+        outln!("{}", serde_json::to_string_pretty(&stats)?)?;
     } else {
+        out!("{}", stats)?;
+    }
+    Ok(())
+}
+
+async fn sc_count(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    // checks with the same filters share "new since the last check"
+    let key = ["filter", "kind", "reason", "repo", "state", "commits"]
+        .iter()
+        .filter_map(|&a| m.value_of(a).map(|v| format!("{}={}", a, v)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let counts = count::check(c, &filt, &key).await?;
+    outln!(
+        "{}",
+        counts.render(m.value_of("format").unwrap().parse().unwrap())
+    )?;
+    Ok(())
+}
+
+async fn sc_feed(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = util::fetch_filtered(&filt, c).await?;
+    let ss = snooze::filter_snoozed(ss, &snooze::load()?, chrono::Utc::now());
+
+    // leave out what ~/.ghnf/filters would unsubscribe, except for ignored threads
+    let ss: Vec<Subscription> = if m.is_present("all") {
         ss
+    } else {
+        match rules::load_optional_rules()? {
+            Some(r) => {
+                let ignored = config::load_ignored()?;
+                ss.into_iter()
+                    .filter(|s| ignored.contains(&s.thread_id) || !r.is_match(s))
+                    .collect()
+            }
+            None => ss,
+        }
     };
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+    let ss = util::sort_and_take(ss, &filt);
 
-    for s in &ss {
-        println!("{}", s);
+    let entries = feed::entries(&ss, c).await?;
+    let xml = feed::atom(
+        &entries,
+        &chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    );
+    match m.value_of("output") {
+        Some(out) => {
+            std::fs::write(out, xml).with_context(|| format!("Failed to write {}", out))?;
+            outln!("Wrote {} entry(ies) to {}", entries.len(), out)?;
+        }
+        None => out!("{}", xml)?,
     }
-    println!("Total entry count: {}", ss.len());
+    Ok(())
+}
+
+async fn sc_digest(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let since = util::parse_time(m.value_of("since").unwrap())?;
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = util::fetch_filtered(&filt, c).await?;
+    let ss = snooze::filter_snoozed(ss, &snooze::load()?, chrono::Utc::now());
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
 
+    let d = digest::collect(&ss, &journal::read_all()?, since, c).await?;
+    if m.value_of("format") == Some("html") {
+        out!("{}", d.html())?;
+    } else {
+        out!("{}", d.markdown())?;
+    }
     Ok(())
 }
 
-async fn sc_remove(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
-    let dry = m.is_present("dry-run");
+async fn sc_suggest(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let parse = |name: &str| -> Result<usize> {
+        m.value_of(name)
+            .unwrap()
+            .parse()
+            .map_err(|_| anyhow!("Invalid argument for <{}>, expected integer", name))
+    };
+    let min_count = parse("min-count")?;
+    let samples = parse("samples")?;
+
+    let filt = parse_filters(m, false, StateFilter::Any)?;
+    let ss = util::fetch_filtered(&filt, c).await?;
+    let ss = util::filter_by_subject_state(ss, filt.state, filt.commits, c).await?;
+
+    // titles already handled by ~/.ghnf/filters don't need a new rule
+    let ss: Vec<Subscription> = if m.is_present("all") {
+        ss
+    } else {
+        match rules::load_optional_rules()? {
+            Some(r) => ss.into_iter().filter(|s| !r.is_match(s)).collect(),
+            None => ss,
+        }
+    };
+    let titles: Vec<&str> = ss.iter().map(|s| s.subject.title.as_str()).collect();
 
-    let ss = {
-        if let Some(i) = m.values_of("thread_ids") {
-            parse_thread_ids(i, c).await
+    let suggestions = suggest::suggest(&titles, min_count, samples);
+    if suggestions.is_empty() {
+        outln!("No suggestion, try a smaller --min-count")?;
+        return Ok(());
+    }
+    for s in suggestions {
+        outln!("{:>6}  {}", s.count, s.regex)?;
+        for t in s.samples {
+            outln!("        e.g. {}", t)?;
+        }
+    }
+    Ok(())
+}
+
+async fn sc_undo(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let since = m.value_of("since").map(util::parse_time).transpose()?;
+    let entries = journal::read_all()?;
+    let targets = journal::undoable(&entries, since);
+
+    if targets.is_empty() {
+        outln!("Nothing to undo")?;
+        return Ok(());
+    }
+
+    if m.is_present("dry-run") {
+        outln!("Following actions are going to be undone:")?;
+        for e in &targets {
+            outln!("{} {} at {}", e.action, e, e.timestamp)?;
+        }
+        return Ok(());
+    }
+
+    let journal = journal::Journal::open()?;
+    let total = targets.len();
+    let mut errors = vec![];
+    // newest first, so that an ignored repository which was watched gets watched again
+    for e in targets.into_iter().rev() {
+        let inverse = e.action.inverse().unwrap();
+        let r = match (inverse, e.thread_id) {
+            (journal::Action::Resubscribe, Some(id)) => Subscription::resubscribe(id, c).await,
+            (journal::Action::WatchRepo, _) => repos::watch(&e.repo, c).await,
+            (journal::Action::UnignoreRepo, _) => repos::unignore(&e.repo, c).await,
+            _ => unreachable!(),
+        };
+        if let Err(err) = r {
+            errors.push(err.context(format!("Failed to {} {}", inverse, e)));
+            continue;
+        }
+        journal.write(&journal::Entry {
+            timestamp: chrono::Utc::now(),
+            run: journal.run(),
+            action: inverse,
+            rule: None,
+            ..e.clone()
+        })?;
+        outln!("{} {}", inverse, e)?;
+    }
+    util::partial_result(errors, total)
+}
+
+async fn sc_repos_ls(c: &Client) -> Result<()> {
+    let (watched, pages) =
+        future::try_join(repos::fetch_watched(c), Subscription::fetch_unread(c)).await?;
+    let ss: Vec<Subscription> = pages.into_iter().flatten().collect();
+    let counts = repos::count_by_repo(&ss);
+
+    let mut rows: Vec<(&str, usize, bool)> = watched
+        .iter()
+        .map(|r| {
+            (
+                r.as_str(),
+                counts.get(r.as_str()).copied().unwrap_or(0),
+                true,
+            )
+        })
+        .collect();
+    rows.extend(
+        counts
+            .iter()
+            .filter(|(r, _)| !watched.iter().any(|w| w == *r))
+            .map(|(r, n)| (*r, *n, false)),
+    );
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    for (repo, count, is_watched) in rows {
+        if is_watched {
+            outln!("{:>5}  {}", count, repo)?;
         } else {
-            util::fetch_filtered(Filters::new(m, true)?, c).await
+            outln!("{:>5}  {} (not watched)", count, repo)?;
         }
-    }?;
-    println!("{} notifications left", ss.len());
+    }
+    outln!(
+        "Watching {} repositories, {} notifications in the inbox",
+        watched.len(),
+        ss.len()
+    )?;
+    Ok(())
+}
 
-    println!("Filtering out open notifications...");
-    let ss: Vec<Subscription> = util::filter_by_subject_state(
-        util::filter_ignored(ss).unwrap(),
-        subscription::SubjectState::Closed,
-        c,
-    )
-    .await?;
-    println!("{} notification(s) left", ss.len());
+async fn sc_repos_remove(m: &ArgMatches<'_>, c: &Client, ignore: bool) -> Result<()> {
+    let globs = m
+        .values_of("GLOB")
+        .unwrap()
+        .map(|g| {
+            rules::glob_to_regex(g)
+                .map(|r| (g, r))
+                .map_err(|e| anyhow!("Invalid glob {:?}: {}", g, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    util::unsubscribe_all(ss, dry, c).await
+    let (watched, pages) =
+        future::try_join(repos::fetch_watched(c), Subscription::fetch_unread(c)).await?;
+    let ss: Vec<Subscription> = pages.into_iter().flatten().collect();
+    let counts = repos::count_by_repo(&ss);
+
+    // unwatched repositories can still notify on participating threads, which ignoring stops
+    let mut candidates = watched.clone();
+    if ignore {
+        candidates.extend(counts.keys().map(|&r| r.to_owned()));
+        candidates.sort();
+        candidates.dedup();
+    }
+    let targets = repos::select(&candidates, &globs);
+
+    if targets.is_empty() {
+        outln!("No repository matched")?;
+        return Err(exit::NoMatches.into());
+    }
+
+    let verb = if ignore { "ignored" } else { "unwatched" };
+    if m.is_present("dry-run") {
+        outln!("Following repositories are going to be {}:", verb)?;
+        for (repo, _) in &targets {
+            outln!(
+                "{} ({} notifications)",
+                repo,
+                counts.get(repo).copied().unwrap_or(0)
+            )?;
+        }
+        return Ok(());
+    }
+
+    let journal = journal::Journal::open()?;
+    let total = targets.len();
+    let mut errors = vec![];
+    for (repo, glob) in targets {
+        let rule = Some(format!("repos {}", glob));
+        let is_watched = watched.iter().any(|w| w == repo);
+        let r = if ignore {
+            repos::ignore(repo, c).await
+        } else {
+            repos::unwatch(repo, c).await
+        };
+        if let Err(e) = r {
+            let action = if ignore { "ignore" } else { "unwatch" };
+            errors.push(e.context(format!("Failed to {} {}", action, repo)));
+            continue;
+        }
+        if ignore {
+            // ignoring replaces watching, so undo has to watch it again
+            if is_watched {
+                journal.record_repo(repo, journal::Action::UnwatchRepo, rule.clone())?;
+            }
+            journal.record_repo(repo, journal::Action::IgnoreRepo, rule)?;
+        } else {
+            journal.record_repo(repo, journal::Action::UnwatchRepo, rule)?;
+        }
+        outln!("{} {}", verb, repo)?;
+    }
+    util::partial_result(errors, total)
+}
+
+async fn sc_read(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    use tokio::time::{sleep, Duration, Instant};
+    const WAIT_INTERVAL: Duration = Duration::from_secs(3);
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    let repo = m.value_of("repo");
+    let before = m
+        .value_of("before")
+        .map_or_else(|| Ok(chrono::Utc::now()), util::parse_time)?
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let ss = Subscription::fetch_unread_before(repo, &before, c).await?;
+    if ss.is_empty() {
+        outln!("No unread notification before {}", before)?;
+        return Err(exit::NoMatches.into());
+    }
+
+    if m.is_present("dry-run") {
+        outln!("Following threads are going to be marked as read:")?;
+        for s in &ss {
+            outln!("{}", s)?;
+        }
+        return Ok(());
+    }
+
+    let done = Subscription::mark_all_as_read(repo, &before, c).await?;
+    let journal = journal::Journal::open()?;
+    for s in &ss {
+        journal.record(s, journal::Action::MarkRead, None)?;
+    }
+
+    if done || m.is_present("no-wait") {
+        outln!("Marked {} thread(s) as read", ss.len())?;
+        return Ok(());
+    }
+
+    tracing::info!(
+        "GitHub is marking {} thread(s) as read asynchronously",
+        ss.len()
+    );
+    let start = Instant::now();
+    loop {
+        sleep(WAIT_INTERVAL).await;
+        let left = Subscription::fetch_unread_before(repo, &before, c)
+            .await?
+            .len();
+        if left == 0 {
+            outln!("Marked {} thread(s) as read", ss.len())?;
+            return Ok(());
+        }
+        if start.elapsed() >= WAIT_TIMEOUT {
+            bail!(
+                "{} thread(s) are still unread after {} secs, GitHub may still be processing",
+                left,
+                WAIT_TIMEOUT.as_secs()
+            );
+        }
+        tracing::info!("{} thread(s) left", left);
+    }
+}
+
+async fn sc_snooze(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let mut snoozed = snooze::load()?;
+    let now = chrono::Utc::now();
+
+    match m.subcommand() {
+        ("ls", Some(_)) => {
+            for e in &snoozed {
+                let due = if e.is_due(now) { " (due)" } else { "" };
+                outln!("{}{}", e, due)?;
+            }
+            outln!("Total entry count: {}", snoozed.len())?;
+        }
+        ("wake", Some(wake_m)) => {
+            let (due, rest): (Vec<_>, Vec<_>) = snoozed.into_iter().partition(|e| e.is_due(now));
+            if due.is_empty() {
+                outln!("No snoozed thread is due")?;
+                return Ok(());
+            }
+            outln!("{} snoozed thread(s) due:", due.len())?;
+            for e in &due {
+                outln!("{}", e)?;
+            }
+            if wake_m.is_present("open") {
+                let browser = browser_command(wake_m)?;
+                for e in &due {
+                    let s = Subscription::from_thread_id(e.thread_id, c).await?;
+                    util::open_url(&s.url_to_open(c, false).await?, browser.as_deref())?;
+                }
+            }
+            snooze::save(&rest)?;
+        }
+        _ => {
+            let id = m
+                .value_of("thread_id")
+                .unwrap()
+                .parse()
+                .map_err(|_| anyhow!("Invalid thread ID"))?;
+            let until = snooze::parse_until(m.value_of("until").unwrap())?;
+            let s = Subscription::from_thread_id(id, c).await?;
+            s.mark_as_read(c).await?;
+            journal::Journal::open()?.record(&s, journal::Action::MarkRead, None)?;
+            let e = snooze::Snoozed::new(&s, until);
+            outln!("Snoozed {}", e)?;
+            snooze::add(&mut snoozed, e);
+            snooze::save(&snoozed)?;
+        }
+    }
+    Ok(())
+}
+
+/// `path` as is if it is a URL, otherwise relative to the API root
+fn api_url(path: &str) -> String {
+    if path.starts_with("https://") || path.starts_with("http://") {
+        path.to_owned()
+    } else {
+        format!("https://api.github.com/{}", path.trim_start_matches('/'))
+    }
 }
 
 async fn sc_request(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
-    let url = m.value_of("URL").unwrap();
-    let resp = c.get(url).send().await?;
-    if resp.status() != 200 {
-        println!("Failed to GET, status code: {}", resp.status());
+    use github_notification_filter::subscription::{check_unexpected_status, StatusError};
+    use github_notification_filter::transport::Response;
+
+    let method: reqwest::Method = m.value_of("method").unwrap().to_uppercase().parse()?;
+    let body = match m.value_of("data") {
+        Some(d) if d.starts_with('@') => Some(
+            std::fs::read_to_string(&d[1..])
+                .map_err(|e| anyhow!("Failed to read {}: {}", &d[1..], e))?,
+        ),
+        d => d.map(ToOwned::to_owned),
+    };
+    let paginate = m.is_present("paginate");
+
+    let print_headers = |resp: &Response| -> std::io::Result<()> {
+        outln!("{} {}", resp.status(), resp.url())?;
+        let mut headers: Vec<_> = resp.headers().iter().collect();
+        headers.sort_by_key(|(k, _)| k.as_str());
+        for (k, v) in headers {
+            outln!(
+                "{}: {}",
+                k,
+                v.to_str().unwrap_or("<Not representable in string>")
+            )?;
+        }
+        outln!()
+    };
+
+    let mut url = Some(api_url(m.value_of("URL").unwrap()));
+    let mut pages = vec![];
+    while let Some(u) = url.take() {
+        let mut req = c.request(method.clone(), &u);
+        if let Some(b) = &body {
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(b.as_str());
+        }
+        let resp = req.send().await?;
+        if m.is_present("include") {
+            print_headers(&resp)?;
+        }
+        let next = resp
+            .headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .and_then(util::get_next_page);
+
+        // any success is expected here, the rest is retried on the rate limit or reported
+        let expected = if resp.status().is_success() {
+            resp.status().as_u16()
+        } else {
+            200
+        };
+        match check_unexpected_status(expected, resp).await {
+            Ok(text) => {
+                pages.push(text);
+                url = next.filter(|_| paginate);
+            }
+            Err(e) => match e.downcast() {
+                Ok(StatusError::RateLimit) => url = Some(u), // retrying
+                Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                Err(e) => bail!(e),
+            },
+        }
     }
-    eprintln!("Headers:\n{:?}", resp.headers());
-    if let Ok(i) = resp.text().await {
-        println!("{}", i);
+
+    let values: Option<Vec<serde_json::Value>> =
+        pages.iter().map(|p| serde_json::from_str(p).ok()).collect();
+    match values {
+        // pages of arrays are merged into one
+        Some(vs) if paginate && vs.iter().all(serde_json::Value::is_array) => {
+            let merged: Vec<_> = vs
+                .into_iter()
+                .flat_map(|v| match v {
+                    serde_json::Value::Array(a) => a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            outln!("{}", serde_json::to_string_pretty(&merged)?)?;
+        }
+        Some(vs) => {
+            for v in vs {
+                outln!("{}", serde_json::to_string_pretty(&v)?)?;
+            }
+        }
+        None => {
+            for p in pages.iter().filter(|p| !p.is_empty()) {
+                outln!("{}", p)?;
+            }
+        }
     }
     Ok(())
 }
 
-#[tokio::main]
-async fn main() {
-    let m = App::new("github-notification-filter")
-        .version(format!("{} (built at {})", crate_version!(), env!("BUILD_DATE")).as_str())
+async fn sc_config_check(m: &ArgMatches<'_>) -> Result<()> {
+    let mut problems = vec![];
+    let mut token_rejected = false;
+
+    let (rules, p) = rules::check_rules()?;
+    problems.extend(p);
+
+    problems.extend(config::check_ignored(&config::read_optional_config(
+        "ignore",
+    )?));
+
+    if !m.is_present("offline") {
+        let gh = create_client(m)?;
+        let c = gh.client();
+        match config::check_token(c).await {
+            Ok(i) => {
+                outln!("Token is valid (authenticated as {})", i.login)?;
+                outln!(
+                    "X-OAuth-Scopes: {}",
+                    i.scopes.as_deref().unwrap_or("<not reported>")
+                )?;
+                let ss: Vec<Subscription> = Subscription::fetch_unread(c)
+                    .await?
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                problems.extend(rules::check_unmatched(&rules, &ss));
+            }
+            Err(e) => match e.downcast::<config::TokenRejected>() {
+                Ok(config::TokenRejected(p)) => {
+                    token_rejected = true;
+                    problems.push(p);
+                }
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    problems.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    for p in &problems {
+        outln!("{}", p)?;
+    }
+
+    let errors = problems
+        .iter()
+        .filter(|p| p.level == config::Level::Error)
+        .count();
+    outln!(
+        "{} error(s), {} warning(s)",
+        errors,
+        problems.len() - errors
+    )?;
+    if errors > 0 {
+        let p = config::Problem::error(
+            "~/.ghnf",
+            None,
+            format!("configuration has {} error(s)", errors),
+        );
+        return Err(if token_rejected {
+            config::TokenRejected(p).into()
+        } else {
+            config::ConfigError(p).into()
+        });
+    }
+    Ok(())
+}
+
+/// Log to stderr as `-v`/`-q` and `--log-format` say, `RUST_LOG` takes precedence
+fn init_logging(m: &ArgMatches<'_>) {
+    use tracing_subscriber::EnvFilter;
+
+    let level = match m.occurrences_of("verbose") {
+        _ if m.is_present("quiet") => "warn",
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        // other crates (hyper, h2) are too chatty below warn
+        EnvFilter::new(format!(
+            "warn,github_notification_filter={0},ghnf={0}",
+            level
+        ))
+    });
+
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(filter);
+    if m.value_of("log-format") == Some("json") {
+        builder.json().init();
+    } else if level == "info" || level == "warn" {
+        // plain messages, as they used to be printed
+        builder
+            .without_time()
+            .with_level(false)
+            .with_target(false)
+            .init();
+    } else {
+        builder.with_target(false).init();
+    }
+}
+
+/// Client for `--mock`, `--replay`, `--record` or GitHub
+fn create_client(m: &ArgMatches<'_>) -> Result<GithubNotifications> {
+    use github_notification_filter::transport::mock::MockTransport;
+    use github_notification_filter::transport::{HttpTransport, RecordTransport, ReplayTransport};
+
+    let client = if let Some(seed) = m.value_of("mock") {
+        Client::new(MockTransport::open(seed.as_ref())?)
+    } else if let Some(dir) = m.value_of("replay") {
+        Client::new(ReplayTransport::open(dir.as_ref())?)
+    } else {
+        let http = HttpTransport::with_token(&config::read_token()?)?;
+        match m.value_of("record") {
+            Some(dir) => Client::new(RecordTransport::new(http, dir.as_ref())?),
+            None => Client::new(http),
+        }
+    };
+    Ok(GithubNotifications::from_client(client))
+}
+
+/// Build `Filters` from the arguments, reading `~/.ghnf/filters` if `with_default_regex` and no `--filter`
+fn parse_filters(
+    m: &ArgMatches<'_>,
+    with_default_regex: bool,
+    default_state: StateFilter,
+) -> Result<Filters> {
+    Ok(Filters {
+        rules: {
+            if let Some(i) = m.value_of("filter") {
+                Some(rules::RuleSet {
+                    rules: vec![rules::Rule::parse(i, "--filter", 0).map_err(|e| anyhow!(e))?],
+                    files: vec![],
+                })
+            } else if with_default_regex {
+                Some(rules::load_rules()?)
+            } else {
+                None
+            }
+        },
+        kind: m.value_of("kind").map(|v| v.parse().unwrap()),
+        reason: m.value_of("reason").map(ToOwned::to_owned),
+        repo: m
+            .value_of("repo")
+            .map(|g| rules::glob_to_regex(g).map_err(|e| anyhow!(e)))
+            .transpose()?,
+        detail: DetailFilter {
+            author: m.value_of("author").map(ToOwned::to_owned),
+            labels: m
+                .values_of("label")
+                .map(|v| v.map(ToOwned::to_owned).collect())
+                .unwrap_or_default(),
+            base: m.value_of("base").map(ToOwned::to_owned),
+            draft: m.is_present("draft"),
+            merged: m.is_present("merged"),
+            not_planned: m.is_present("not-planned"),
+        },
+        state: if m.is_present("closed") {
+            StateFilter::Closed
+        } else {
+            m.value_of("state")
+                .map_or(default_state, |v| v.parse().unwrap())
+        },
+        commits: m
+            .value_of("commits")
+            .map_or_else(Default::default, |v| v.parse().unwrap()),
+        sort: m.value_of("sort").map(|v| Sort {
+            key: v.parse().unwrap(),
+            desc: m.value_of("order") == Some("desc"),
+        }),
+        count: m
+            .value_of("count")
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| anyhow!("Invalid argument for <count>, expected integer"))
+            })
+            .transpose()?,
+    })
+}
+
+fn filter_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("filter")
+        .help("regex to filter")
+        .long("filter")
+        .short("f")
+        .takes_value(true)
+}
+
+fn kind_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("kind")
+        .help("specify a kind of notification (\"commit\", \"issue\", or \"pr\")")
+        .short("k")
+        .takes_value(true)
+        .possible_values(&["commit", "issue", "pr"])
+}
+
+fn state_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("state")
+            .help("only threads in the state [default: closed for remove, any otherwise]")
+            .long("state")
+            .takes_value(true)
+            .possible_values(&["open", "closed", "merged", "closed-unmerged", "any"]),
+        Arg::with_name("commits")
+            .help("whether commits pass the state filter [default: include]")
+            .long("commits")
+            .takes_value(true)
+            .possible_values(&["include", "exclude"]),
+    ]
+}
+
+fn sort_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+    [
+        Arg::with_name("sort")
+            .help("sort threads by the key")
+            .long("sort")
+            .takes_value(true)
+            .possible_values(&["updated", "repo", "type", "title", "reason"]),
+        Arg::with_name("order")
+            .help("sort order [default: asc]")
+            .long("order")
+            .takes_value(true)
+            .possible_values(&["asc", "desc"])
+            .requires("sort"),
+        Arg::with_name("count")
+            .help("only process specified count (the first ones after --sort)")
+            .short("n")
+            .takes_value(true),
+    ]
+}
+
+fn detail_filter_args<'a, 'b>() -> [Arg<'a, 'b>; 6] {
+    [
+        Arg::with_name("author")
+            .help("only threads opened by the user (e.g. \"dependabot[bot]\")")
+            .long("author")
+            .takes_value(true),
+        Arg::with_name("label")
+            .help("only threads with the label (can be repeated, all must match)")
+            .long("label")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("base")
+            .help("only pull requests targeting the base branch")
+            .long("base")
+            .takes_value(true),
+        Arg::with_name("draft")
+            .help("only draft pull requests")
+            .long("draft"),
+        Arg::with_name("merged")
+            .help("only merged pull requests")
+            .long("merged"),
+        Arg::with_name("not-planned")
+            .help("only issues closed as not planned")
+            .long("not-planned"),
+    ]
+}
+
+/// Visible subcommands of `build_app` in the order declared, for the man page
+///
+/// clap 2 has no public accessor for subcommands, keep this in sync when adding one.
+const SUBCOMMANDS: &[&[&str]] = &[
+    &["remove"],
+    &["plan"],
+    &["apply"],
+    &["open"],
+    &["stats"],
+    &["count"],
+    &["feed"],
+    &["digest"],
+    &["suggest"],
+    &["show"],
+    &["list"],
+    &["watch"],
+    &["undo"],
+    &["read"],
+    &["snooze"],
+    &["snooze", "ls"],
+    &["snooze", "wake"],
+    &["repos"],
+    &["repos", "ls"],
+    &["repos", "unwatch"],
+    &["repos", "ignore"],
+    &["config"],
+    &["config", "check"],
+    &["request"],
+    &["completions"],
+    &["man"],
+];
+
+fn build_app<'a, 'b>() -> App<'a, 'b> {
+    App::new("github-notification-filter")
+        .version(concat!(crate_version!(), " (built at ", env!("BUILD_DATE"), ")"))
         .setting(AppSettings::ColoredHelp)
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .args(&[
+            Arg::with_name("record")
+                .help("save every request and response as fixtures in the directory")
+                .long("record")
+                .takes_value(true)
+                .value_name("dir")
+                .global(true),
+            Arg::with_name("replay")
+                .help("serve responses from fixtures in the directory (saved by --record) instead of GitHub")
+                .long("replay")
+                .takes_value(true)
+                .value_name("dir")
+                .global(true)
+                .conflicts_with("record"),
+            Arg::with_name("mock")
+                .help("run against an in-memory fake of GitHub seeded from the JSON file")
+                .long("mock")
+                .takes_value(true)
+                .value_name("seed.json")
+                .global(true)
+                .conflicts_with_all(&["record", "replay"]),
+            Arg::with_name("verbose")
+                .help("log more: -v for every request, -vv for everything")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .global(true),
+            Arg::with_name("quiet")
+                .help("only log warnings and errors")
+                .short("q")
+                .long("quiet")
+                .global(true)
+                .conflicts_with("verbose"),
+            Arg::with_name("log-format")
+                .help("format of the logs on stderr")
+                .long("log-format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .global(true),
+        ])
         .subcommand(
             SubCommand::with_name("remove")
                 .about("Unsubscribe notifications by regex")
@@ -131,94 +1231,502 @@ async fn main() {
                         .help("Do not unsubscribe, but list threads to be unsubscribed")
                         .long("dry-run")
                         .short("d"),
-                    Arg::with_name("count")
-                        .help("only process specified count (the order is undetermined)")
-                        .short("n")
-                        .takes_value(true),
-                    Arg::with_name("filter")
-                        .help("regex to filter")
-                        .long("filter")
-                        .short("f")
-                        .takes_value(true),
-                    Arg::with_name("kind")
-                        .help("specify a kind of notification (\"commit\", \"issue\", or \"pr\"")
-                        .short("k")
-                        .takes_value(true)
-                        .possible_values(&["commit", "issue", "pr"]),
+                    filter_arg(),
+                    kind_arg(),
                     Arg::with_name("thread_ids")
-                        .conflicts_with("filter")
-                        .conflicts_with("kind")
-                        .min_values(1)
+                        .min_values(1),
                 ])
+                .args(&state_args())
+                .args(&sort_args())
+                .args(&detail_filter_args())
                 .visible_alias("rm"),
         )
+        .subcommand(
+            SubCommand::with_name("plan")
+                .about("Save threads to be unsubscribed (as `remove` selects them) to a plan file")
+                .args(&[
+                    Arg::with_name("output")
+                        .help("file to write the plan to")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true),
+                    Arg::with_name("action")
+                        .help("what to do with the threads")
+                        .long("action")
+                        .takes_value(true)
+                        .possible_values(&["unsubscribe", "read"])
+                        .default_value("unsubscribe"),
+                    filter_arg(),
+                    kind_arg(),
+                    Arg::with_name("thread_ids")
+                        .min_values(1),
+                ])
+                .args(&state_args())
+                .args(&sort_args())
+                .args(&detail_filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("apply")
+                .about("Run a plan file, skipping threads updated since it was made")
+                .args(&[
+                    Arg::with_name("PLAN").index(1).required(true),
+                    Arg::with_name("dry-run")
+                        .help("Do not change anything, but list what would be applied")
+                        .long("dry-run")
+                        .short("d"),
+                ]),
+        )
         .subcommand(
             SubCommand::with_name("open")
                 .about("Open a thread, or all filtered thread with the web browser")
                 .args(&[
-                    Arg::with_name("count")
-                        .help("only process specified count (the order is undetermined)")
-                        .short("n")
+                    // opening the whole inbox by accident would be too many tabs
+                    Arg::with_name("thread_ids").min_values(1).required_unless_one(&[
+                        "filter",
+                        "kind",
+                        "count",
+                        "closed",
+                        "state",
+                        "author",
+                        "label",
+                        "base",
+                        "draft",
+                        "merged",
+                        "not-planned",
+                    ]),
+                    filter_arg(),
+                    kind_arg(),
+                    Arg::with_name("print-url")
+                        .help("print urls instead of opening them")
+                        .long("print-url")
+                        .conflicts_with_all(&["mark-read", "browser", "max-tabs"]),
+                    Arg::with_name("browser")
+                        .help("browser command, \"{}\" is replaced with the url (default: ~/.ghnf/browser or the system default)")
+                        .long("browser")
                         .takes_value(true),
-                    Arg::with_name("thread_ids")
-                        .conflicts_with("filter")
-                        .conflicts_with("kind")
-                        .min_values(1)
-                        .required(true),
-                    Arg::with_name("filter")
-                        .conflicts_with("thread_ids")
-                        .help("regex to filter")
-                        .long("filter")
-                        .short("f")
+                    Arg::with_name("max-tabs")
+                        .help("open at most this many pages at once, waiting between batches")
+                        .long("max-tabs")
                         .takes_value(true),
-                    Arg::with_name("kind")
-                        .conflicts_with("thread_ids")
-                        .help("specify a kind of notification (\"commit\", \"issue\", or \"pr\"")
-                        .short("k")
+                    Arg::with_name("batch-delay")
+                        .help("seconds to wait between batches of --max-tabs")
+                        .long("batch-delay")
                         .takes_value(true)
-                        .possible_values(&["commit", "issue", "pr"]),
+                        .default_value("3"),
+                    Arg::with_name("mark-read")
+                        .help("mark threads as read once they are opened")
+                        .long("mark-read"),
+                    Arg::with_name("latest-comment")
+                        .help("open the latest comment instead of the top of the thread")
+                        .long("latest-comment"),
                     Arg::with_name("closed")
-                        .help("open only closed notifications")
+                        .help("open only closed notifications (same as --state closed)")
                         .long("closed")
-                        .short("c"),
-                ]),
+                        .short("c")
+                        .conflicts_with("state"),
+                ])
+                .args(&state_args())
+                .args(&sort_args())
+                .args(&detail_filter_args()),
         )
         .subcommand(
-            SubCommand::with_name("list")
-                .about("List unread subscriptions")
+            SubCommand::with_name("stats")
+                .about("Show statistics of unread notifications by repository, type, reason, state and age")
                 .args(&[
-                    Arg::with_name("filter")
-                        .help("regex to filter")
-                        .long("filter")
-                        .short("f")
+                    Arg::with_name("format")
+                        .help("output format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                    Arg::with_name("top")
+                        .help("number of repositories and title prefixes to show")
+                        .long("top")
+                        .takes_value(true)
+                        .default_value("10"),
+                    Arg::with_name("with-state")
+                        .help("also count by state (needs a request per thread)")
+                        .long("with-state"),
+                    filter_arg(),
+                    kind_arg(),
+                ])
+                .args(&state_args())
+                .args(&detail_filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("count")
+                .about("Print the number of unread notifications for status bars, and how many are new")
+                .args(&[
+                    Arg::with_name("format")
+                        .help("output format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["plain", "waybar", "i3blocks", "tmux"])
+                        .default_value("plain"),
+                    Arg::with_name("reason")
+                        .help("only notifications for the reason (e.g. \"review_requested\", \"mention\")")
+                        .long("reason")
+                        .takes_value(true),
+                    Arg::with_name("repo")
+                        .help("only notifications of repositories matching the glob (e.g. \"rust-lang/*\")")
+                        .long("repo")
+                        .takes_value(true),
+                    filter_arg(),
+                    kind_arg(),
+                ])
+                .args(&state_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("feed")
+                .about("Write unread notifications as an Atom feed, leaving out threads matched by ~/.ghnf/filters")
+                .args(&[
+                    Arg::with_name("output")
+                        .help("file to write the feed to [default: stdout]")
+                        .long("output")
+                        .short("o")
                         .takes_value(true),
-                    Arg::with_name("kind")
-                        .help("specify a kind of notification (\"commit\", \"issue\", or \"pr\"")
-                        .short("k")
+                    Arg::with_name("all")
+                        .help("also include threads matched by ~/.ghnf/filters")
+                        .long("all"),
+                    filter_arg(),
+                    kind_arg(),
+                ])
+                .args(&state_args())
+                .args(&sort_args())
+                .args(&detail_filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("digest")
+                .about("Summarize threads updated recently by repository and reason, and what rules unsubscribed")
+                .args(&[
+                    Arg::with_name("since")
+                        .help("window of the digest (e.g. \"1d\", \"2021-01-31\")")
+                        .long("since")
+                        .takes_value(true)
+                        .default_value("1d"),
+                    Arg::with_name("format")
+                        .help("output format")
+                        .long("format")
                         .takes_value(true)
-                        .possible_values(&["commit", "issue", "pr"]),
+                        .possible_values(&["md", "html"])
+                        .default_value("md"),
+                    filter_arg(),
+                    kind_arg(),
+                ])
+                .args(&state_args())
+                .args(&detail_filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("suggest")
+                .about("Suggest rules for ~/.ghnf/filters from common titles in the inbox")
+                .args(&[
+                    Arg::with_name("min-count")
+                        .help("minimum number of titles for a suggestion")
+                        .long("min-count")
+                        .takes_value(true)
+                        .default_value("3"),
+                    Arg::with_name("samples")
+                        .help("number of sample titles to show for each suggestion")
+                        .long("samples")
+                        .takes_value(true)
+                        .default_value("3"),
+                    Arg::with_name("all")
+                        .help("also use titles already matched by ~/.ghnf/filters")
+                        .long("all"),
+                    kind_arg(),
+                ])
+                .args(&state_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Show the detail of a thread with its latest comment")
+                .arg(Arg::with_name("thread_id").index(1).required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List unread subscriptions")
+                .args(&[
+                    filter_arg(),
+                    kind_arg(),
                     Arg::with_name("closed")
-                        .help("show only closed notifications")
+                        .help("show only closed notifications (same as --state closed)")
                         .long("closed")
-                        .short("c"),
+                        .short("c")
+                        .conflicts_with("state"),
                 ])
+                .args(&state_args())
+                .args(&sort_args())
+                .args(&detail_filter_args())
+                .arg(
+                    Arg::with_name("group-by")
+                        .help("print threads in sections with counts")
+                        .long("group-by")
+                        .takes_value(true)
+                        .possible_values(&["repo", "type", "reason"]),
+                )
                 .visible_alias("ls"),
         )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Keep polling notifications and unsubscribe (or mark as read) matched threads")
+                .args(&[
+                    Arg::with_name("dry-run")
+                        .help("Do not change anything, but log threads to be handled")
+                        .long("dry-run")
+                        .short("d"),
+                    Arg::with_name("action")
+                        .help("what to do with matched threads")
+                        .long("action")
+                        .takes_value(true)
+                        .possible_values(&["unsubscribe", "read"])
+                        .default_value("unsubscribe"),
+                    Arg::with_name("interval")
+                        .help("minimum seconds between polls (GitHub's X-Poll-Interval is respected)")
+                        .long("interval")
+                        .takes_value(true),
+                    filter_arg().help("regex to filter (disables reloading ~/.ghnf/filters)"),
+                    kind_arg(),
+                ])
+                .args(&state_args())
+                .args(&detail_filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("undo")
+                .about("Undo unsubscribes and repository unwatches/ignores of the last run (or since a time), using ~/.ghnf/journal")
+                .args(&[
+                    Arg::with_name("last")
+                        .help("undo the last run which did anything undoable (default)")
+                        .long("last"),
+                    Arg::with_name("since")
+                        .help("undo everything done since the time (e.g. \"2h\", \"2021-01-31\")")
+                        .long("since")
+                        .takes_value(true)
+                        .conflicts_with("last"),
+                    Arg::with_name("dry-run")
+                        .help("Do not undo, but list actions to be undone")
+                        .long("dry-run")
+                        .short("d"),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("read")
+                .about("Mark all notifications (or those of a repository) updated before a time as read at once")
+                .args(&[
+                    Arg::with_name("all")
+                        .help("mark notifications of all repositories")
+                        .long("all"),
+                    Arg::with_name("repo")
+                        .help("mark notifications of the repository (\"owner/name\")")
+                        .long("repo")
+                        .takes_value(true),
+                    Arg::with_name("before")
+                        .help("only notifications updated before the time (e.g. \"3d\", \"2021-01-31\") [default: now]")
+                        .long("before")
+                        .takes_value(true),
+                    Arg::with_name("no-wait")
+                        .help("Do not wait for GitHub to finish when it processes the request asynchronously")
+                        .long("no-wait"),
+                    Arg::with_name("dry-run")
+                        .help("Do not mark as read, but list threads to be marked as read")
+                        .long("dry-run")
+                        .short("d"),
+                ])
+                .group(
+                    ArgGroup::with_name("target")
+                        .args(&["all", "repo"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("snooze")
+                .about("Mark a thread as read and bring it back later (e.g. `ghnf snooze 123 3d`)")
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .setting(AppSettings::ArgsNegateSubcommands)
+                .args(&[
+                    Arg::with_name("thread_id").index(1).required(true),
+                    Arg::with_name("until")
+                        .help("duration (e.g. \"3d\") or time (e.g. \"2021-01-31\") to snooze until")
+                        .index(2)
+                        .required(true),
+                ])
+                .subcommand(SubCommand::with_name("ls").about("List snoozed threads").visible_alias("list"))
+                .subcommand(
+                    SubCommand::with_name("wake")
+                        .about("List snoozed threads which are due, and stop tracking them")
+                        .args(&[
+                            Arg::with_name("open")
+                                .help("Open the due threads with the browser")
+                                .long("open"),
+                            Arg::with_name("browser")
+                                .help("browser command, \"{}\" is replaced with the url (default: ~/.ghnf/browser or the system default)")
+                                .long("browser")
+                                .takes_value(true)
+                                .requires("open"),
+                        ]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("repos")
+                .about("Manage watched repositories")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("ls")
+                        .about("List watched repositories with their notification counts in the inbox")
+                        .visible_alias("list"),
+                )
+                .subcommand(
+                    SubCommand::with_name("unwatch")
+                        .about("Stop watching repositories matching the globs (e.g. \"rust-lang/*\")")
+                        .args(&[
+                            Arg::with_name("GLOB").index(1).required(true).multiple(true),
+                            Arg::with_name("dry-run")
+                                .help("Do not unwatch, but list repositories to be unwatched")
+                                .long("dry-run")
+                                .short("d"),
+                        ]),
+                )
+                .subcommand(
+                    SubCommand::with_name("ignore")
+                        .about("Ignore all notifications from repositories matching the globs")
+                        .args(&[
+                            Arg::with_name("GLOB").index(1).required(true).multiple(true),
+                            Arg::with_name("dry-run")
+                                .help("Do not ignore, but list repositories to be ignored")
+                                .long("dry-run")
+                                .short("d"),
+                        ]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Manage configuration files under ~/.ghnf")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Validate filters, ignore and token, reporting each problem with its line")
+                        .arg(
+                            Arg::with_name("offline")
+                                .help("Skip validating the token and matching against the inbox")
+                                .long("offline"),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("request")
-                .about("Make a GET request to URL using ~/.ghnf/token (for devs)")
-                .arg(Arg::with_name("URL").index(1).required(true))
+                .about("Make a request to the API using ~/.ghnf/token (for devs)")
+                .args(&[
+                    Arg::with_name("URL")
+                        .help("URL, or path relative to https://api.github.com (e.g. notifications)")
+                        .index(1)
+                        .required(true),
+                    Arg::with_name("method")
+                        .help("HTTP method")
+                        .short("X")
+                        .long("method")
+                        .takes_value(true)
+                        .default_value("GET"),
+                    Arg::with_name("data")
+                        .help("request body (JSON), or @file to read it from a file")
+                        .short("d")
+                        .long("data")
+                        .takes_value(true),
+                    Arg::with_name("paginate")
+                        .help("follow `Link: rel=\"next\"` and merge the pages")
+                        .long("paginate"),
+                    Arg::with_name("include")
+                        .help("print the status and headers of each response")
+                        .short("i")
+                        .long("include"),
+                ])
                 .visible_alias("req"),
         )
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Print a completion script for the shell")
+                .arg(
+                    Arg::with_name("shell")
+                        .index(1)
+                        .required(true)
+                        .possible_values(&["bash", "zsh", "fish"]),
+                ),
+        )
+        .subcommand(SubCommand::with_name("man").about("Print the man page"))
+        .subcommand(
+            SubCommand::with_name("complete")
+                .about("Print candidates for dynamic completion, from ~/.ghnf/recent")
+                .setting(AppSettings::Hidden)
+                .arg(
+                    Arg::with_name("what")
+                        .index(1)
+                        .required(true)
+                        .possible_values(&["thread-ids", "repos"]),
+                ),
+        )
+}
+
+#[tokio::main]
+async fn main() {
+    let m = build_app().get_matches();
+
+    init_logging(&m);
+
+    if let Err(e) = run(&m).await {
+        exit::exit_with(&e);
+    }
+}
+
+async fn run(m: &ArgMatches<'_>) -> Result<()> {
+    // these don't need the network nor the token
+    match m.subcommand() {
+        ("completions", Some(sub_m)) => {
+            out!(
+                "{}",
+                completion::script(sub_m.value_of("shell").unwrap().parse().unwrap())
+            )?;
+            return Ok(());
+        }
+        ("man", Some(_)) => {
+            out!("{}", completion::man())?;
+            return Ok(());
+        }
+        ("complete", Some(sub_m)) => {
+            return completion::print_candidates(sub_m.value_of("what").unwrap())
+        }
+        ("config", Some(sub_m)) => {
+            return match sub_m.subcommand() {
+                ("check", Some(check_m)) => sc_config_check(check_m).await,
+                _ => unreachable!(),
+            }
+        }
+        _ => {}
+    }
 
-    let c = util::create_client().unwrap();
+    let gh = create_client(m)?;
+    let c = gh.client();
     match m.subcommand() {
-        ("open", Some(sub_m)) => sc_open(sub_m, &c).await,
-        ("list", Some(sub_m)) => sc_list(sub_m, &c).await,
-        ("remove", Some(sub_m)) => sc_remove(sub_m, &c).await,
-        ("request", Some(sub_m)) => sc_request(sub_m, &c).await,
+        ("open", Some(sub_m)) => sc_open(sub_m, c).await,
+        ("list", Some(sub_m)) => sc_list(sub_m, c).await,
+        ("remove", Some(sub_m)) => sc_remove(sub_m, c).await,
+        ("request", Some(sub_m)) => sc_request(sub_m, c).await,
+        ("show", Some(sub_m)) => sc_show(sub_m, c).await,
+        ("stats", Some(sub_m)) => sc_stats(sub_m, c).await,
+        ("count", Some(sub_m)) => sc_count(sub_m, c).await,
+        ("digest", Some(sub_m)) => sc_digest(sub_m, c).await,
+        ("feed", Some(sub_m)) => sc_feed(sub_m, c).await,
+        ("suggest", Some(sub_m)) => sc_suggest(sub_m, c).await,
+        ("undo", Some(sub_m)) => sc_undo(sub_m, c).await,
+        ("read", Some(sub_m)) => sc_read(sub_m, c).await,
+        ("snooze", Some(sub_m)) => sc_snooze(sub_m, c).await,
+        ("repos", Some(sub_m)) => match sub_m.subcommand() {
+            ("ls", Some(_)) => sc_repos_ls(c).await,
+            ("unwatch", Some(rm_m)) => sc_repos_remove(rm_m, c, false).await,
+            ("ignore", Some(rm_m)) => sc_repos_remove(rm_m, c, true).await,
+            _ => unreachable!(),
+        },
+        ("plan", Some(sub_m)) => sc_plan(sub_m, c).await,
+        ("apply", Some(sub_m)) => sc_apply(sub_m, c).await,
+        ("watch", Some(sub_m)) => watch::run(sub_m, c).await,
         _ => unreachable!(),
     }
-    .unwrap()
 }
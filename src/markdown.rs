@@ -0,0 +1,210 @@
+use std::fmt::Write;
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const STRIKE: &str = "\x1b[9m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders GitHub flavored Markdown as plain text, decorated with ANSI escapes if `color`
+struct Renderer {
+    out: String,
+    color: bool,
+    /// nesting of lists, with the next number for ordered ones
+    lists: Vec<Option<u64>>,
+    quote: usize,
+    in_code: bool,
+    /// cells written in the current table row
+    cells: usize,
+    links: Vec<String>,
+}
+
+impl Renderer {
+    fn style(&mut self, s: &str) {
+        if self.color {
+            self.out.push_str(s);
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.quote {
+            self.out.push_str("│ ");
+        }
+    }
+
+    /// end a block with a blank line, unless inside a list
+    fn end_block(&mut self) {
+        let len = self
+            .out
+            .trim_end_matches(|c: char| c.is_whitespace() || c == '│')
+            .len();
+        self.out.truncate(len);
+        self.newline();
+        if self.lists.is_empty() {
+            self.newline();
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        if self.in_code {
+            for (i, l) in s.split_inclusive('\n').enumerate() {
+                if i > 0 || self.out.ends_with('\n') || self.out.ends_with("│ ") {
+                    self.out.push_str("    ");
+                }
+                self.out.push_str(l.strip_suffix('\n').unwrap_or(l));
+                if l.ends_with('\n') {
+                    self.newline();
+                }
+            }
+        } else {
+            self.out.push_str(s);
+        }
+    }
+
+    fn event(&mut self, ev: Event<'_>) {
+        match ev {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(s) => self.text(&s),
+            Event::Code(s) => {
+                self.style(CYAN);
+                if !self.color {
+                    self.out.push('`');
+                }
+                self.out.push_str(&s);
+                if !self.color {
+                    self.out.push('`');
+                }
+                self.style(RESET);
+            }
+            Event::SoftBreak | Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.out.push_str("────────");
+                self.end_block();
+            }
+            // GitHub templates are full of comments, which are not shown on the web
+            Event::Html(s) | Event::InlineHtml(s) if !s.trim_start().starts_with("<!--") => {
+                self.out.push_str(&s);
+            }
+            Event::TaskListMarker(done) => self.out.push_str(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Heading { .. } => {
+                self.style(BOLD);
+                self.style(UNDERLINE);
+            }
+            Tag::BlockQuote(_) => {
+                self.quote += 1;
+                self.out.push_str("│ ");
+            }
+            Tag::CodeBlock(_) => self.in_code = true,
+            Tag::List(n) => {
+                if !self.lists.is_empty() {
+                    self.newline();
+                }
+                self.lists.push(n);
+            }
+            Tag::Item => {
+                let depth = self.lists.len();
+                self.out.push_str(&"  ".repeat(depth.saturating_sub(1)));
+                match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        write!(self.out, "{}. ", n).unwrap();
+                        *n += 1;
+                    }
+                    _ => self.out.push_str("• "),
+                }
+            }
+            Tag::TableCell => {
+                if self.cells > 0 {
+                    self.out.push_str(" | ");
+                }
+                self.cells += 1;
+            }
+            Tag::Emphasis => self.style(ITALIC),
+            Tag::Strong => self.style(BOLD),
+            Tag::Strikethrough => self.style(STRIKE),
+            Tag::Link { dest_url, .. } => {
+                self.style(UNDERLINE);
+                self.links.push(dest_url.into_string());
+            }
+            Tag::Image { dest_url, .. } => {
+                self.out.push_str("[image: ");
+                self.links.push(dest_url.into_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.style(RESET);
+                self.end_block();
+            }
+            TagEnd::Paragraph | TagEnd::Table => self.end_block(),
+            TagEnd::BlockQuote(_) => {
+                self.quote = self.quote.saturating_sub(1);
+                self.end_block();
+            }
+            TagEnd::CodeBlock => {
+                self.in_code = false;
+                if self.lists.is_empty() {
+                    self.newline();
+                }
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.newline();
+                }
+            }
+            TagEnd::Item if !self.out.ends_with('\n') => self.newline(),
+            TagEnd::TableHead | TagEnd::TableRow => {
+                self.cells = 0;
+                self.newline();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => self.style(RESET),
+            TagEnd::Link => {
+                self.style(RESET);
+                if let Some(url) = self.links.pop() {
+                    if !self.out.ends_with(&url) {
+                        write!(self.out, " <{}>", url).unwrap();
+                    }
+                }
+            }
+            TagEnd::Image => {
+                if let Some(url) = self.links.pop() {
+                    write!(self.out, " <{}>]", url).unwrap();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Render Markdown for the terminal
+pub fn render(text: &str, color: bool) -> String {
+    let mut r = Renderer {
+        out: String::new(),
+        color,
+        lists: vec![],
+        quote: 0,
+        in_code: false,
+        cells: 0,
+        links: vec![],
+    };
+    let opts = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+    for ev in Parser::new_ext(text, opts) {
+        r.event(ev);
+    }
+    r.out.trim_end().to_owned()
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{Action, Journal};
+use crate::subscription::{Subscription, ThreadID};
+use crate::transport::Client;
+use crate::util::Filters;
+
+/// A reviewable set of actions, written by `plan` and run by `apply`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub created_at: DateTime<Utc>,
+    /// the command line which created the plan
+    pub command: Vec<String>,
+    pub threads: Vec<PlannedThread>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedThread {
+    pub thread_id: ThreadID,
+    pub repo: String,
+    pub title: String,
+    /// the thread is skipped if it has been updated since the plan was made
+    pub updated_at: String,
+    pub action: Action,
+    /// the rule which selected the thread, if any
+    pub rule: Option<String>,
+}
+
+impl Plan {
+    pub fn new(ss: &[Subscription], action: Action, filt: &Filters, command: Vec<String>) -> Self {
+        Self {
+            created_at: Utc::now(),
+            command,
+            threads: ss
+                .iter()
+                .map(|s| PlannedThread {
+                    thread_id: s.thread_id,
+                    repo: s.repo_name.clone(),
+                    title: s.subject.title.clone(),
+                    updated_at: s.updated_at.clone(),
+                    action,
+                    rule: filt.matching_rule(s).map(ToString::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn read(path: &str) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        serde_json::from_str(&text).with_context(|| format!("Malformed plan file {}", path))
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path))
+    }
+}
+
+/// Result of applying a planned thread
+pub enum Outcome {
+    Applied(Subscription),
+    /// updated since the plan was made
+    Skipped(Subscription),
+}
+
+/// Apply a planned thread unless it was updated since the plan was made
+///
+/// Without `journal`, only checks whether it would be applied.
+pub async fn apply_thread(
+    t: &PlannedThread,
+    journal: Option<&Journal>,
+    c: &Client,
+) -> Result<Outcome> {
+    let s = Subscription::from_thread_id(t.thread_id, c)
+        .await
+        .with_context(|| format!("could not retrieve: {}", t.thread_id))?;
+    if s.updated_at != t.updated_at {
+        return Ok(Outcome::Skipped(s));
+    }
+
+    if let Some(journal) = journal {
+        if t.action == Action::Unsubscribe {
+            s.unsubscribe(c).await?;
+        }
+        journal.record(&s, t.action, t.rule.clone())?;
+        s.mark_as_read(c).await?;
+    }
+    Ok(Outcome::Applied(s))
+}
@@ -0,0 +1,65 @@
+//! Threads seen in recent runs, kept in `~/.ghnf/recent` for shell completion
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::subscription::{Subscription, ThreadID};
+
+/// Threads kept at most, the least recently updated are dropped
+const MAX_THREADS: usize = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recent {
+    pub thread_id: ThreadID,
+    pub repo: String,
+    pub title: String,
+    pub updated_at: String,
+}
+
+impl From<&Subscription> for Recent {
+    fn from(s: &Subscription) -> Self {
+        Self {
+            thread_id: s.thread_id,
+            repo: s.repo_name.clone(),
+            title: s.subject.title.clone(),
+            updated_at: s.updated_at.clone(),
+        }
+    }
+}
+
+/// Read `~/.ghnf/recent`, which is optional, the most recently updated first
+pub fn load() -> Result<Vec<Recent>> {
+    let text = config::read_optional_config("recent")?;
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    serde_json::from_str(&text).context("Failed to parse ~/.ghnf/recent")
+}
+
+/// Add `ss` to `~/.ghnf/recent`, replacing the threads already there
+///
+/// Does nothing without `~/.ghnf`, which is not worth creating only for completion.
+pub fn save(ss: &[Subscription]) -> Result<()> {
+    let path = config::config_path("recent")?;
+    if !path.parent().is_some_and(std::path::Path::is_dir) {
+        return Ok(());
+    }
+
+    let mut recent = load().unwrap_or_default();
+    recent.retain(|r| !ss.iter().any(|s| s.thread_id == r.thread_id));
+    recent.extend(ss.iter().map(Recent::from));
+    recent.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    recent.truncate(MAX_THREADS);
+
+    std::fs::write(&path, serde_json::to_string(&recent)?)
+        .with_context(|| format!("Failed to write {}", config::display_path(&path)))
+}
+
+/// Repositories of the threads, sorted and deduplicated
+pub fn repos(recent: &[Recent]) -> Vec<&str> {
+    let mut repos: Vec<&str> = recent.iter().map(|r| r.repo.as_str()).collect();
+    repos.sort_unstable();
+    repos.dedup();
+    repos
+}
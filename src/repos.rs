@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::subscription::gh_objects::Repository;
+use crate::subscription::{check_unexpected_status, StatusError, Subscription};
+use crate::transport::Client;
+
+/// Fetch the full names of the repositories the user watches
+pub async fn fetch_watched(c: &Client) -> Result<Vec<String>> {
+    let mut ret = vec![];
+    let mut url = Some("https://api.github.com/user/subscriptions?per_page=100".to_owned());
+    while let Some(u) = url.take() {
+        let resp = c.get(&u).send().await?;
+        let next = resp
+            .headers()
+            .get("link")
+            .and_then(|v| v.to_str().ok())
+            .and_then(crate::util::get_next_page);
+        match check_unexpected_status(200, resp).await {
+            Ok(s) => {
+                ret.extend(
+                    serde_json::from_str::<Vec<Repository>>(&s)?
+                        .into_iter()
+                        .map(|r| r.full_name),
+                );
+                url = next;
+            }
+            Err(e) => match e.downcast() {
+                Ok(StatusError::RateLimit) => url = Some(u), // retrying
+                Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+    Ok(ret)
+}
+
+/// Notification counts per repository in `ss`
+pub fn count_by_repo(ss: &[Subscription]) -> BTreeMap<&str, usize> {
+    let mut ret = BTreeMap::new();
+    for s in ss {
+        *ret.entry(s.repo_name.as_str()).or_insert(0) += 1;
+    }
+    ret
+}
+
+/// Repositories in `repos` matching any of `globs` (source and compiled), with the first matching glob
+pub fn select<'a, 'g>(repos: &'a [String], globs: &'g [(&str, Regex)]) -> Vec<(&'a str, &'g str)> {
+    repos
+        .iter()
+        .filter_map(|r| {
+            globs
+                .iter()
+                .find(|(_, g)| g.is_match(r))
+                .map(|(src, _)| (r.as_str(), *src))
+        })
+        .collect()
+}
+
+async fn put_subscription(repo: &str, body: &'static str, c: &Client) -> Result<()> {
+    let url = format!("https://api.github.com/repos/{}/subscription", repo);
+
+    loop {
+        let resp = c
+            .put(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?;
+        match check_unexpected_status(200, resp).await {
+            Ok(_) => return Ok(()),
+            Err(e) => match e.downcast() {
+                Ok(StatusError::RateLimit) => { /* retrying */ }
+                Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+}
+
+async fn delete_subscription(repo: &str, c: &Client) -> Result<()> {
+    let url = format!("https://api.github.com/repos/{}/subscription", repo);
+
+    loop {
+        let resp = c.delete(&url).send().await?;
+        match check_unexpected_status(204, resp).await {
+            Ok(_) => return Ok(()),
+            Err(e) => match e.downcast() {
+                Ok(StatusError::RateLimit) => { /* retrying */ }
+                Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+}
+
+/// Stop watching a repository (notifications for participating threads still arrive)
+pub async fn unwatch(repo: &str, c: &Client) -> Result<()> {
+    delete_subscription(repo, c).await
+}
+
+/// Ignore all notifications from a repository
+pub async fn ignore(repo: &str, c: &Client) -> Result<()> {
+    put_subscription(repo, r#"{"ignored":true}"#, c).await
+}
+
+/// Watch a repository, undoing `unwatch`
+pub async fn watch(repo: &str, c: &Client) -> Result<()> {
+    put_subscription(repo, r#"{"subscribed":true}"#, c).await
+}
+
+/// Stop ignoring a repository, undoing `ignore`
+pub async fn unignore(repo: &str, c: &Client) -> Result<()> {
+    delete_subscription(repo, c).await
+}
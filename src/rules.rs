@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+
+use crate::config::{self, ConfigError, Problem};
+use crate::subscription::gh_objects::SubjectType;
+use crate::subscription::Subscription;
+
+/// A rule read from a line of `~/.ghnf/filters` (or a file included from it)
+///
+/// Syntax of a line:
+/// ```text
+/// # comment
+/// %include <file>
+/// [!] [repo:<glob>] [type:<kind>] [case:sensitive|insensitive] <regex>
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rule {
+    /// where the rule comes from (`~/.ghnf/filters`, `--filter`, ...)
+    pub origin: String,
+    /// line number in `origin` (1-origin, 0 for the command line)
+    pub line: usize,
+    pub source: String,
+    /// `!pattern`, excluding threads even if other rules match
+    pub negated: bool,
+    pub repo: Option<Regex>,
+    pub kind: Option<SubjectType>,
+    /// `case:sensitive`
+    pub case_sensitive: bool,
+    pub regex: Regex,
+}
+
+impl Rule {
+    pub fn parse(source: &str, origin: &str, line: usize) -> Result<Self, String> {
+        let (negated, mut rest) = source
+            .strip_prefix('!')
+            .map_or((false, source), |r| (true, r.trim_start()));
+
+        let mut repo = None;
+        let mut kind = None;
+        let mut case_sensitive = false;
+        while !rest.is_empty() {
+            let (tok, tail) = rest
+                .split_once(char::is_whitespace)
+                .map_or((rest, ""), |(t, r)| (t, r.trim_start()));
+            if let Some(v) = tok.strip_prefix("repo:") {
+                if v.is_empty() {
+                    return Err("empty glob for `repo:`".to_owned());
+                }
+                repo = Some(glob_to_regex(v)?);
+            } else if let Some(v) = tok.strip_prefix("type:") {
+                kind = Some(v.parse()?);
+            } else if let Some(v) = tok.strip_prefix("case:") {
+                case_sensitive = match v {
+                    "sensitive" => true,
+                    "insensitive" => false,
+                    _ => {
+                        return Err(format!(
+                            "unknown value {:?} for `case:` (expected \"sensitive\" or \"insensitive\")",
+                            v
+                        ))
+                    }
+                };
+            } else {
+                break;
+            }
+            rest = tail;
+        }
+
+        if rest.is_empty() && repo.is_none() && kind.is_none() {
+            return Err("empty pattern".to_owned());
+        }
+
+        let regex = RegexBuilder::new(rest)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            origin: origin.to_owned(),
+            line,
+            source: source.to_owned(),
+            negated,
+            repo,
+            kind,
+            case_sensitive,
+            regex,
+        })
+    }
+
+    /// check the scope (`repo:`, `type:`) and the title regex, ignoring negation
+    pub fn is_match(&self, s: &Subscription) -> bool {
+        self.repo.as_ref().is_none_or(|r| r.is_match(&s.repo_name))
+            && self.kind.is_none_or(|k| k == s.subject.r#type)
+            && self.regex.is_match(&s.subject.title)
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            write!(f, "{} {}", self.origin, self.source)
+        } else {
+            write!(f, "{}:{} {}", self.origin, self.line, self.source)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+    /// files the rules were read from, with their modification time at that point
+    pub files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl RuleSet {
+    /// the first positive rule matching `s`, unless a negated rule matches it
+    pub fn matching_rule(&self, s: &Subscription) -> Option<&Rule> {
+        if self.rules.iter().any(|r| r.negated && r.is_match(s)) {
+            return None;
+        }
+        self.rules.iter().find(|r| !r.negated && r.is_match(s))
+    }
+
+    pub fn is_match(&self, s: &Subscription) -> bool {
+        self.matching_rule(s).is_some()
+    }
+
+    /// whether any of the files the rules were read from has been modified since
+    pub fn is_stale(&self) -> bool {
+        self.files.iter().any(|(f, t)| modified(f) != *t)
+    }
+}
+
+/// Convert a glob (`*` and `?` only) to a case-insensitive anchored regex
+pub fn glob_to_regex(glob: &str) -> Result<Regex, String> {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    RegexBuilder::new(&re)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn resolve_include(name: &str, dir: &Path) -> PathBuf {
+    if let Some(rest) = name.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    dir.join(name)
+}
+
+fn read_rules_rec(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    rules: &mut Vec<Rule>,
+    problems: &mut Vec<Problem>,
+) {
+    let origin = config::display_path(path);
+    let text = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            problems.push(Problem::error(
+                &origin,
+                None,
+                format!("Failed to read: {}", e),
+            ));
+            return;
+        }
+    };
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    files.push(canonical.clone());
+    stack.push(canonical);
+
+    for (i, l) in text.lines().enumerate() {
+        let l = l.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = l.strip_prefix("%include") {
+            if !name.is_empty() && !name.starts_with(char::is_whitespace) {
+                let directive = l.split_whitespace().next().unwrap_or(l);
+                problems.push(Problem::error(
+                    &origin,
+                    Some(i + 1),
+                    format!("unknown directive {}", directive),
+                ));
+                continue;
+            }
+            let name = name.trim();
+            if name.is_empty() {
+                problems.push(Problem::error(
+                    &origin,
+                    Some(i + 1),
+                    "missing file name for %include".to_owned(),
+                ));
+                continue;
+            }
+            let inc = resolve_include(name, path.parent().unwrap_or_else(|| Path::new(".")));
+            let inc_canonical = inc.canonicalize().unwrap_or_else(|_| inc.clone());
+            if stack.contains(&inc_canonical) {
+                problems.push(Problem::error(
+                    &origin,
+                    Some(i + 1),
+                    format!("recursive %include of {}", config::display_path(&inc)),
+                ));
+            } else if !inc.exists() {
+                problems.push(Problem::error(
+                    &origin,
+                    Some(i + 1),
+                    format!(
+                        "included file {} does not exist",
+                        config::display_path(&inc)
+                    ),
+                ));
+            } else {
+                read_rules_rec(&inc, stack, files, rules, problems);
+            }
+            continue;
+        }
+        match Rule::parse(l, &origin, i + 1) {
+            Ok(r) => rules.push(r),
+            Err(e) => problems.push(Problem::error(&origin, Some(i + 1), e)),
+        }
+    }
+
+    stack.pop();
+}
+
+/// Read rules from `path` and the files it includes, collecting every problem
+///
+/// Also returns the files which were read.
+pub fn read_rules(path: &Path) -> (Vec<Rule>, Vec<PathBuf>, Vec<Problem>) {
+    let mut files = vec![];
+    let mut rules = vec![];
+    let mut problems = vec![];
+    read_rules_rec(path, &mut vec![], &mut files, &mut rules, &mut problems);
+    (rules, files, problems)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Load `~/.ghnf/filters`, failing at the first problem
+pub fn load_rules() -> Result<RuleSet> {
+    let (rules, files, problems) = read_rules(&config::config_path("filters")?);
+    if let Some(p) = problems.into_iter().next() {
+        return Err(ConfigError(p).into());
+    }
+    Ok(RuleSet {
+        rules,
+        files: files
+            .into_iter()
+            .map(|f| {
+                let t = modified(&f);
+                (f, t)
+            })
+            .collect(),
+    })
+}
+
+/// `load_rules`, or `None` without `~/.ghnf/filters`
+pub fn load_optional_rules() -> Result<Option<RuleSet>> {
+    match std::fs::metadata(config::config_path("filters")?) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        _ => load_rules().map(Some),
+    }
+}
+
+/// Static checks on `filters`: broken lines, broken includes and duplicates
+pub fn check_rules() -> Result<(Vec<Rule>, Vec<Problem>)> {
+    let (rules, _, mut problems) = read_rules(&config::config_path("filters")?);
+
+    // as parsed, so that the order of scopes doesn't matter and only case-insensitive patterns
+    // are compared ignoring case
+    let mut seen = HashMap::<_, &Rule>::new();
+    for r in &rules {
+        let pattern = r.regex.as_str();
+        let key = (
+            r.negated,
+            r.repo.as_ref().map(|g| g.as_str().to_owned()),
+            r.kind,
+            r.case_sensitive,
+            if r.case_sensitive {
+                pattern.to_owned()
+            } else {
+                pattern.to_lowercase()
+            },
+        );
+        if let Some(first) = seen.get(&key) {
+            problems.push(Problem::warning(
+                &r.origin,
+                Some(r.line),
+                format!("duplicate of the rule at {}:{}", first.origin, first.line),
+            ));
+        } else {
+            seen.insert(key, r);
+        }
+    }
+
+    Ok((rules, problems))
+}
+
+/// Report rules which match none of `ss`
+pub fn check_unmatched(rules: &[Rule], ss: &[Subscription]) -> Vec<Problem> {
+    rules
+        .iter()
+        .filter(|r| !ss.iter().any(|s| r.is_match(s)))
+        .map(|r| {
+            Problem::warning(
+                &r.origin,
+                Some(r.line),
+                format!("rule {:?} matches no current notification", r.source),
+            )
+        })
+        .collect()
+}
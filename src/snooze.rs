@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::subscription::{Subscription, ThreadID};
+use crate::util;
+
+/// A thread marked as read until `until`, kept in `~/.ghnf/snoozed`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snoozed {
+    pub thread_id: ThreadID,
+    pub repo: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+impl Snoozed {
+    pub fn new(s: &Subscription, until: DateTime<Utc>) -> Self {
+        Self {
+            thread_id: s.thread_id,
+            repo: s.repo_name.clone(),
+            title: s.subject.title.clone(),
+            created_at: Utc::now(),
+            until,
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.until <= now
+    }
+}
+
+impl std::fmt::Display for Snoozed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} : {} ({}) until {}",
+            self.repo, self.title, self.thread_id, self.until
+        )
+    }
+}
+
+/// Parse when to wake up: a duration from now (e.g. "3d") or a time
+pub fn parse_until(s: &str) -> Result<DateTime<Utc>> {
+    util::parse_duration(s).map_or_else(
+        |_| util::parse_time(s),
+        |d| {
+            Utc::now()
+                .checked_add_signed(d)
+                .ok_or_else(|| anyhow!("time out of range: {:?} from now", s))
+        },
+    )
+}
+
+/// Read `~/.ghnf/snoozed`, which is optional
+pub fn load() -> Result<Vec<Snoozed>> {
+    let text = config::read_optional_config("snoozed")?;
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    serde_json::from_str(&text).context("Failed to parse ~/.ghnf/snoozed")
+}
+
+pub fn save(snoozed: &[Snoozed]) -> Result<()> {
+    let path = config::config_path("snoozed")?;
+    std::fs::write(&path, serde_json::to_string_pretty(snoozed)?)
+        .with_context(|| format!("Failed to write {}", config::display_path(&path)))
+}
+
+/// Add `s` to `snoozed`, replacing the previous snooze of the same thread
+pub fn add(snoozed: &mut Vec<Snoozed>, s: Snoozed) {
+    snoozed.retain(|e| e.thread_id != s.thread_id);
+    snoozed.push(s);
+    snoozed.sort_by_key(|e| e.until);
+}
+
+/// Remove threads still snoozed at `now` from `ss`
+pub fn filter_snoozed(
+    ss: Vec<Subscription>,
+    snoozed: &[Snoozed],
+    now: DateTime<Utc>,
+) -> Vec<Subscription> {
+    ss.into_iter()
+        .filter(|s| {
+            !snoozed
+                .iter()
+                .any(|e| e.thread_id == s.thread_id && !e.is_due(now))
+        })
+        .collect()
+}
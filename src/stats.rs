@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::subscription::gh_objects::SubjectType;
+use crate::subscription::{SubjectState, Subscription};
+use crate::transport::Client;
+
+#[derive(Debug, Serialize)]
+pub struct Count {
+    pub key: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub total: usize,
+    pub repos: Vec<Count>,
+    pub types: Vec<Count>,
+    pub reasons: Vec<Count>,
+    /// only available with `--with-state`
+    pub states: Option<Vec<Count>>,
+    pub ages: Vec<Count>,
+    pub title_prefixes: Vec<Count>,
+}
+
+/// Count by key, most frequent first
+fn count_by<'a>(
+    ss: impl Iterator<Item = &'a Subscription>,
+    key: impl Fn(&Subscription) -> String,
+) -> Vec<Count> {
+    let mut map = HashMap::<String, usize>::new();
+    for s in ss {
+        *map.entry(key(s)).or_default() += 1;
+    }
+    let mut ret: Vec<Count> = map
+        .into_iter()
+        .map(|(key, count)| Count { key, count })
+        .collect();
+    ret.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    ret
+}
+
+const AGE_BUCKETS: [(i64, &str); 5] = [
+    (1, "< 1 day"),
+    (7, "1-7 days"),
+    (30, "7-30 days"),
+    (90, "30-90 days"),
+    (i64::MAX, ">= 90 days"),
+];
+
+fn age_bucket(updated_at: &str, now: DateTime<Utc>) -> &'static str {
+    DateTime::parse_from_rfc3339(updated_at).map_or("unknown", |t| {
+        let days = (now - t.with_timezone(&Utc)).num_days();
+        AGE_BUCKETS
+            .iter()
+            .find(|(max, _)| days < *max)
+            .map_or("unknown", |(_, label)| label)
+    })
+}
+
+/// The first word of the title, e.g. `Bump`, `[CI]` or `chore(deps):`
+pub fn title_prefix(title: &str) -> String {
+    title.split_whitespace().next().unwrap_or("").to_owned()
+}
+
+/// Fetched state for display, `-` for threads without state
+pub(crate) async fn state_label(s: &Subscription, c: &Client) -> Result<String> {
+    if matches!(
+        s.subject.r#type,
+        SubjectType::Commit | SubjectType::Discussion
+    ) {
+        return Ok("-".to_owned());
+    }
+    let d = s.subject_detail(c).await?;
+    Ok(match d.state {
+        _ if d.merged == Some(true) => "merged",
+        Some(SubjectState::Open) => "open",
+        Some(SubjectState::Closed) => "closed",
+        None => "-",
+    }
+    .to_owned())
+}
+
+pub async fn collect(
+    ss: &[Subscription],
+    with_state: bool,
+    top: usize,
+    c: &Client,
+) -> Result<Stats> {
+    let now = Utc::now();
+
+    let states = if with_state {
+        let mut labels = HashMap::new();
+        for chunk in ss.chunks(crate::util::CHUNK_SIZE) {
+            let r =
+                futures::future::try_join_all(chunk.iter().map(|s| async move {
+                    Result::<_>::Ok((s.thread_id, state_label(s, c).await?))
+                }))
+                .await?;
+            labels.extend(r);
+        }
+        Some(count_by(ss.iter(), |s| labels[&s.thread_id].clone()))
+    } else {
+        None
+    };
+
+    let mut repos = count_by(ss.iter(), |s| s.repo_name.clone());
+    repos.truncate(top);
+
+    let mut ages = count_by(ss.iter(), |s| age_bucket(&s.updated_at, now).to_owned());
+    // in the order of buckets rather than counts
+    ages.sort_by_key(|a| {
+        AGE_BUCKETS
+            .iter()
+            .position(|(_, l)| *l == a.key)
+            .unwrap_or(AGE_BUCKETS.len())
+    });
+
+    let mut title_prefixes = count_by(ss.iter(), |s| title_prefix(&s.subject.title));
+    title_prefixes.retain(|p| p.count > 1);
+    title_prefixes.truncate(top);
+
+    Ok(Stats {
+        total: ss.len(),
+        repos,
+        types: count_by(ss.iter(), |s| s.subject.r#type.to_string()),
+        reasons: count_by(ss.iter(), |s| s.reason.clone()),
+        states,
+        ages,
+        title_prefixes,
+    })
+}
+
+fn write_section(
+    f: &mut std::fmt::Formatter<'_>,
+    title: &str,
+    counts: &[Count],
+) -> std::fmt::Result {
+    writeln!(f, "\n{}:", title)?;
+    for c in counts {
+        writeln!(f, "{:>6}  {}", c.count, c.key)?;
+    }
+    Ok(())
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Total: {} thread(s)", self.total)?;
+        write_section(f, "By repository", &self.repos)?;
+        write_section(f, "By type", &self.types)?;
+        write_section(f, "By reason", &self.reasons)?;
+        if let Some(states) = &self.states {
+            write_section(f, "By state", states)?;
+        }
+        write_section(f, "By age (since the last update)", &self.ages)?;
+        write_section(f, "Common title prefixes", &self.title_prefixes)
+    }
+}
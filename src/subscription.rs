@@ -1,8 +1,11 @@
-use anyhow::{anyhow, Result};
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, Context, Result};
 use once_cell::unsync::OnceCell;
-use reqwest::Client;
 use reqwest::StatusCode;
 
+use crate::transport::{Client, Response};
+
 pub mod gh_objects;
 use gh_objects::Notification;
 pub use gh_objects::SubjectDetail;
@@ -16,17 +19,42 @@ pub struct Subscription {
     pub thread_id: ThreadID,
     pub repo_name: String,
     pub updated_at: String, //TODO: use correct type
+    pub reason: String,
+    pub last_read_at: Option<String>,
     subject_detail: OnceCell<SubjectDetail>,
 }
 
-impl From<Notification> for Subscription {
-    fn from(n: Notification) -> Self {
-        Self {
+impl TryFrom<Notification> for Subscription {
+    type Error = anyhow::Error;
+
+    fn try_from(n: Notification) -> Result<Self> {
+        let thread_id =
+            n.id.parse()
+                .with_context(|| format!("malformed thread id {:?}", n.id))?;
+        Ok(Self {
             subject: n.subject,
-            thread_id: n.id.parse().unwrap(),
+            thread_id,
             repo_name: n.repository.full_name,
             subject_detail: OnceCell::new(),
             updated_at: n.updated_at,
+            reason: n.reason,
+            last_read_at: n.last_read_at,
+        })
+    }
+}
+
+/// The fields read from the API, e.g. to cache threads between runs
+impl From<&Subscription> for Notification {
+    fn from(s: &Subscription) -> Self {
+        Self {
+            id: s.thread_id.to_string(),
+            repository: gh_objects::Repository {
+                full_name: s.repo_name.clone(),
+            },
+            subject: s.subject.clone(),
+            updated_at: s.updated_at.clone(),
+            reason: s.reason.clone(),
+            last_read_at: s.last_read_at.clone(),
         }
     }
 }
@@ -45,40 +73,73 @@ impl std::fmt::Display for Subscription {
     }
 }
 
+/// Result of `Subscription::poll_unread`
+pub struct Polled {
+    /// `None` when nothing changed since the last poll
+    pub subscriptions: Option<Vec<Subscription>>,
+    pub last_modified: Option<String>,
+    /// `X-Poll-Interval` in seconds
+    pub poll_interval: Option<u64>,
+}
+
 #[derive(Debug, thiserror::Error)]
-enum StatusError {
+pub enum StatusError {
     #[error("Rate limit handled")]
     RateLimit,
     #[error("Unexpected status")]
-    Unexpected(String),
+    Unexpected(UnexpectedStatus),
+}
+
+/// A response with a status other than expected, and not retried
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct UnexpectedStatus {
+    pub status: StatusCode,
+    /// The primary rate limit is exhausted (`X-RateLimit-Remaining: 0`), see `X-RateLimit-Reset`
+    pub rate_limited: bool,
+    message: String,
+}
+
+impl UnexpectedStatus {
+    /// The token is missing, wrong or expired
+    pub fn is_auth_failure(&self) -> bool {
+        self.status == StatusCode::UNAUTHORIZED
+    }
 }
 
-async fn check_unexpected_status(expected: u16, resp: reqwest::Response) -> Result<String> {
+pub async fn check_unexpected_status(expected: u16, resp: Response) -> Result<String> {
     use tokio::time;
     let now = time::Instant::now();
-    if resp.status() != expected {
-        return Err(if let Some(t) = resp
+    if resp.status() == expected {
+        Ok(resp.text())
+    } else {
+        Err(if let Some(t) = resp
             .headers()
             .get("retry-after")
             .and_then(|v| v.to_str().ok())
             .and_then(|s| s.parse().ok())
         {
-            eprintln!("Rate limit exceeded, wait for {} secs", t);
-            let _ = resp.text().await?;
+            tracing::warn!("Rate limit exceeded, wait for {} secs", t);
             time::sleep_until(now + time::Duration::from_secs(t)).await;
             StatusError::RateLimit
         } else {
-            let errmsg = fmt_unexpected_status(expected, resp).await;
-            StatusError::Unexpected(errmsg)
+            StatusError::Unexpected(UnexpectedStatus {
+                status: resp.status(),
+                rate_limited: matches!(
+                    resp.status(),
+                    StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+                ) && resp
+                    .headers()
+                    .get("x-ratelimit-remaining")
+                    .is_some_and(|v| v == "0"),
+                message: fmt_unexpected_status(expected, resp),
+            })
         }
-        .into());
-    } else {
-        let text = resp.text().await?;
-        return Ok(text);
+        .into())
     }
 }
 
-async fn fmt_unexpected_status(expected: u16, resp: reqwest::Response) -> String {
+fn fmt_unexpected_status(expected: u16, resp: Response) -> String {
     use std::fmt::Write;
 
     let mut ret = format!(
@@ -98,18 +159,19 @@ async fn fmt_unexpected_status(expected: u16, resp: reqwest::Response) -> String
         }
     }
 
-    write!(
-        ret,
-        "\nBody: {}",
-        resp.text()
-            .await
-            .unwrap_or_else(|_| String::from("<Failed to get body>"))
-    )
-    .unwrap();
+    write!(ret, "\nBody: {}", resp.text()).unwrap();
 
     ret
 }
 
+/// `/notifications`, or `/repos/{repo}/notifications` for a repository
+fn notifications_url(repo: Option<&str>) -> String {
+    repo.map_or_else(
+        || "https://api.github.com/notifications".to_owned(),
+        |r| format!("https://api.github.com/repos/{}/notifications", r),
+    )
+}
+
 impl Subscription {
     pub async fn from_thread_id(id: ThreadID, c: &Client) -> Result<Self> {
         let url = format!("https://api.github.com/notifications/threads/{}", id);
@@ -117,64 +179,124 @@ impl Subscription {
             let resp = c.get(&url).send().await?;
             match check_unexpected_status(200, resp).await {
                 Ok(s) => {
-                    return serde_json::from_str::<Notification>(&s)
-                        .map_err(Into::into)
-                        .map(Into::into)
+                    return Self::try_from(serde_json::from_str::<Notification>(&s)?);
                 }
                 Err(e) => match e.downcast() {
                     Ok(StatusError::RateLimit) => { /* retrying */ }
-                    Ok(StatusError::Unexpected(s)) => return Err(anyhow!(s)),
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
                     Err(e) => return Err(e),
                 },
             }
         }
     }
 
-    pub async fn open(&self, c: &Client) -> Result<()> {
-        open::that(self.html_url(c).await?)
-            .map(|_| ()) // discard ExitStatus
-            .map_err(Into::into)
+    /// get url to open with the browser, optionally pointing at the latest comment
+    pub async fn url_to_open(&self, c: &Client, latest_comment: bool) -> Result<String> {
+        // Discussions don't have detail (until GitHub fixes FIXME!)
+        if self.subject.url.is_none() {
+            return Ok(format!("https://github.com/{}/discussions", self.repo_name));
+        }
+        if latest_comment {
+            if let Some(comment) = self.latest_comment(c).await? {
+                return Ok(comment.html_url);
+            }
+        }
+        self.html_url(c).await
     }
 
     // TODO: rewrite with Stream
     pub async fn fetch_unread(client: &Client) -> Result<Vec<Vec<Self>>> {
         let url = "https://api.github.com/notifications";
         let head = client.head(url).send().await?;
-        let last_page = crate::util::get_last_page(head.headers()["Link"].to_str().unwrap());
-
-        let mut futs = vec![];
-
-        for i in 1..=last_page {
-            let i_str = i.to_string();
-            futs.push(async move {
-                loop {
-                    let resp = client
-                        .get("https://api.github.com/notifications")
-                        .query(&[("page", &i_str)])
-                        .send()
-                        .await?;
-
-                    match check_unexpected_status(200, resp).await {
-                        Ok(s) => {
-                            return Ok(serde_json::from_str::<Vec<Notification>>(&s)?
-                                .into_iter()
-                                .map(Into::into));
-                        }
-                        Err(e) => match e.downcast() {
-                            Ok(StatusError::RateLimit) => { /* retrying */ }
-                            Ok(StatusError::Unexpected(s)) => return Err(anyhow!(s)),
-                            Err(e) => return Err(e),
-                        },
-                    }
+        let last_page = head
+            .headers()
+            .get("Link")
+            .and_then(|v| v.to_str().ok())
+            .map_or(1, crate::util::get_last_page);
+
+        let futs = (1..=last_page).map(|i| Self::fetch_page(client, i));
+
+        futures::future::try_join_all(futs).await
+    }
+
+    async fn fetch_page(client: &Client, page: usize) -> Result<Vec<Self>> {
+        let page = page.to_string();
+        loop {
+            let resp = client
+                .get("https://api.github.com/notifications")
+                .query(&[("page", &page)])
+                .send()
+                .await?;
+
+            match check_unexpected_status(200, resp).await {
+                Ok(s) => {
+                    return serde_json::from_str::<Vec<Notification>>(&s)?
+                        .into_iter()
+                        .map(Self::try_from)
+                        .collect();
                 }
-            });
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => { /* retrying */ }
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
         }
+    }
 
-        Ok(futures::future::try_join_all(futs)
-            .await?
-            .into_iter()
-            .map(Iterator::collect)
-            .collect())
+    /// Fetch unread notifications unless nothing changed since `last_modified`
+    ///
+    /// Requests answered with 304 don't count against the rate limit.
+    pub async fn poll_unread(client: &Client, last_modified: Option<&str>) -> Result<Polled> {
+        loop {
+            let mut req = client.get("https://api.github.com/notifications");
+            if let Some(l) = last_modified {
+                req = req.header(reqwest::header::IF_MODIFIED_SINCE, l);
+            }
+            let resp = req.send().await?;
+
+            let header = |k: &str| {
+                resp.headers()
+                    .get(k)
+                    .and_then(|v| v.to_str().ok())
+                    .map(ToOwned::to_owned)
+            };
+            let poll_interval = header("x-poll-interval").and_then(|s| s.parse().ok());
+            let new_last_modified = header("last-modified");
+            let last_page = header("link").map_or(1, |l| crate::util::get_last_page(&l));
+
+            if resp.status() == StatusCode::NOT_MODIFIED {
+                return Ok(Polled {
+                    subscriptions: None,
+                    last_modified: last_modified.map(ToOwned::to_owned),
+                    poll_interval,
+                });
+            }
+
+            match check_unexpected_status(200, resp).await {
+                Ok(s) => {
+                    let mut ss = serde_json::from_str::<Vec<Notification>>(&s)?
+                        .into_iter()
+                        .map(Self::try_from)
+                        .collect::<Result<Vec<Self>>>()?;
+                    let pages = futures::future::try_join_all(
+                        (2..=last_page).map(|i| Self::fetch_page(client, i)),
+                    )
+                    .await?;
+                    ss.extend(pages.into_iter().flatten());
+                    return Ok(Polled {
+                        subscriptions: Some(ss),
+                        last_modified: new_last_modified,
+                        poll_interval,
+                    });
+                }
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => { /* retrying */ }
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
     }
 
     pub async fn unsubscribe(&self, c: &Client) -> Result<()> {
@@ -189,7 +311,32 @@ impl Subscription {
                 Ok(_) => return Ok(()),
                 Err(e) => match e.downcast() {
                     Ok(StatusError::RateLimit) => { /* retrying */ }
-                    Ok(StatusError::Unexpected(s)) => return Err(anyhow!(s)),
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// subscribe to a thread again, undoing `unsubscribe`
+    pub async fn resubscribe(id: ThreadID, c: &Client) -> Result<()> {
+        let url = format!(
+            "https://api.github.com/notifications/threads/{}/subscription",
+            id
+        );
+
+        loop {
+            let resp = c
+                .put(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(r#"{"ignored":false}"#)
+                .send()
+                .await?;
+            match check_unexpected_status(200, resp).await {
+                Ok(_) => return Ok(()),
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => { /* retrying */ }
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
                     Err(e) => return Err(e),
                 },
             }
@@ -208,44 +355,121 @@ impl Subscription {
                 Ok(_) => return Ok(()),
                 Err(e) => match e.downcast() {
                     Ok(StatusError::RateLimit) => { /* retrying */ }
-                    Ok(StatusError::Unexpected(s)) => return Err(anyhow!(s)),
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
                     Err(e) => return Err(e),
                 },
             }
         }
     }
 
-    /// get url for subject's html location
-    pub async fn html_url(&self, c: &Client) -> Result<String> {
-        Ok(self.subject_detail(c).await?.html_url.to_owned())
+    /// Fetch unread notifications updated before `before` (RFC 3339), optionally only in `repo`
+    pub async fn fetch_unread_before(
+        repo: Option<&str>,
+        before: &str,
+        c: &Client,
+    ) -> Result<Vec<Self>> {
+        let mut ret = vec![];
+        let mut req = Some(
+            c.get(notifications_url(repo))
+                .query(&[("before", before), ("per_page", "100")]),
+        );
+        while let Some(r) = req.take() {
+            let retry = Some(r.clone());
+            let resp = r.send().await?;
+            let next = resp
+                .headers()
+                .get("link")
+                .and_then(|v| v.to_str().ok())
+                .and_then(crate::util::get_next_page);
+            match check_unexpected_status(200, resp).await {
+                Ok(s) => {
+                    for n in serde_json::from_str::<Vec<Notification>>(&s)? {
+                        ret.push(Self::try_from(n)?);
+                    }
+                    req = next.map(|u| c.get(&u));
+                }
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => req = retry, // retrying
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+        Ok(ret)
     }
 
-    /// get subject state (i.e. open or closed)
-    pub async fn subject_state(&self, c: &Client) -> Result<Option<gh_objects::SubjectState>> {
-        Ok(self.subject_detail(c).await?.state)
+    /// Mark all notifications updated before `before` (RFC 3339) as read, optionally only in `repo`
+    ///
+    /// Returns `false` if GitHub processes the request asynchronously (202 Accepted).
+    pub async fn mark_all_as_read(repo: Option<&str>, before: &str, c: &Client) -> Result<bool> {
+        let body = serde_json::json!({ "last_read_at": before, "read": true }).to_string();
+
+        loop {
+            let resp = c
+                .put(notifications_url(repo))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await?;
+            if resp.status() == StatusCode::ACCEPTED {
+                return Ok(false);
+            }
+            match check_unexpected_status(205, resp).await {
+                Ok(_) => return Ok(true),
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => { /* retrying */ }
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// get url for subject's html location
+    pub async fn html_url(&self, c: &Client) -> Result<String> {
+        Ok(self.subject_detail(c).await?.html_url.clone())
     }
 
     async fn fetch_subject_detail(&self, c: &Client) -> Result<SubjectDetail> {
-        let url = &self
-            .subject
-            .url
-            .as_ref()
-            .expect("tried to fetch subject_detail of Discussions");
+        // Discussions don't have detail (until GitHub fixes FIXME!)
+        let Some(url) = &self.subject.url else {
+            return Err(anyhow!("{} has no detail", self));
+        };
 
         loop {
-            let resp = c.get(*url).send().await?;
+            let resp = c.get(url).send().await?;
             match check_unexpected_status(200, resp).await {
                 Ok(s) => return serde_json::from_str(&s).map_err(Into::into),
                 Err(e) => match e.downcast() {
                     Ok(StatusError::RateLimit) => { /* retrying */ }
-                    Ok(StatusError::Unexpected(s)) => return Err(anyhow!(s)),
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// get the latest comment (or the subject itself if there is no comment)
+    pub async fn latest_comment(&self, c: &Client) -> Result<Option<gh_objects::Comment>> {
+        let Some(url) = &self.subject.latest_comment_url else {
+            return Ok(None);
+        };
+
+        loop {
+            let resp = c.get(url).send().await?;
+            match check_unexpected_status(200, resp).await {
+                Ok(s) => return serde_json::from_str(&s).map(Some).map_err(Into::into),
+                Err(e) => match e.downcast() {
+                    Ok(StatusError::RateLimit) => { /* retrying */ }
+                    Ok(StatusError::Unexpected(e)) => return Err(e.into()),
                     Err(e) => return Err(e),
                 },
             }
         }
     }
 
-    async fn subject_detail(&self, c: &Client) -> Result<&SubjectDetail> {
+    /// get subject detail (issue, pull request or commit), fetched once and cached
+    pub async fn subject_detail(&self, c: &Client) -> Result<&SubjectDetail> {
         if self.subject_detail.get().is_none() {
             let res = self.fetch_subject_detail(c).await?;
             self.subject_detail
@@ -1,30 +1,29 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Notification {
     pub id: String,
     pub repository: Repository,
     pub subject: Subject,
     pub updated_at: String,
+    pub reason: String,
+    pub last_read_at: Option<String>,
     /* fields not currently used:
     pub url: String,
-    pub last_read_at: Option<String>,
-    pub reason: String,
     pub subscription_url: String,
     pub unread: bool,
     */
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Subject {
     pub title: String,
     pub url: Option<String>, // not exists for discussions (This must be a FIXME, GitHub!)
     pub r#type: SubjectType,
-    // not currently used
-    //latest_comment_url: Option<String>,
+    pub latest_comment_url: Option<String>,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 #[serde(field_identifier)]
 pub enum SubjectType {
     Commit,
@@ -33,6 +32,18 @@ pub enum SubjectType {
     PullRequest,
 }
 
+/// As the API names them (`field_identifier` can't be derived for serializing)
+impl Serialize for SubjectType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            Self::Commit => "Commit",
+            Self::Discussion => "Discussion",
+            Self::Issue => "Issue",
+            Self::PullRequest => "PullRequest",
+        })
+    }
+}
+
 impl std::fmt::Display for SubjectType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -44,6 +55,24 @@ impl std::fmt::Display for SubjectType {
     }
 }
 
+impl std::str::FromStr for SubjectType {
+    type Err = String;
+
+    /// parse the short names used on the command line and in `filters`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "commit" => Ok(Self::Commit),
+            "discussion" => Ok(Self::Discussion),
+            "issue" => Ok(Self::Issue),
+            "pr" => Ok(Self::PullRequest),
+            _ => Err(format!(
+                "unknown kind {:?} (expected \"commit\", \"discussion\", \"issue\", or \"pr\")",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SubjectState {
@@ -57,9 +86,48 @@ pub struct SubjectDetail {
     pub html_url: String,
     pub state: Option<SubjectState>, // doesn't exist for commits
     pub title: Option<String>,       // doesn't exist for commits
+    pub user: Option<User>,          // doesn't exist for commits
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    pub draft: Option<bool>,          // only for pull requests
+    pub merged: Option<bool>,         // only for pull requests
+    pub base: Option<Branch>,         // only for pull requests
+    pub state_reason: Option<String>, // only for issues
+    pub created_at: Option<String>,   // doesn't exist for commits
+    pub closed_at: Option<String>,    // doesn't exist for commits
+}
+
+impl SubjectDetail {
+    pub fn is_not_planned(&self) -> bool {
+        self.state_reason.as_deref() == Some("not_planned")
+    }
+}
+
+/// A comment, or the issue/pull request itself when it has no comments
+#[derive(Clone, Debug, Deserialize)]
+pub struct Comment {
+    pub html_url: String,
+    pub body: Option<String>,
+    pub user: Option<User>,
+    pub created_at: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Label {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Branch {
+    pub r#ref: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Repository {
     pub full_name: String,
 }
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use regex::RegexBuilder;
+
+/// A candidate rule for `~/.ghnf/filters`
+#[derive(Debug)]
+pub struct Suggestion {
+    pub regex: String,
+    /// number of titles the regex matches
+    pub count: usize,
+    pub samples: Vec<String>,
+}
+
+/// Tokens with digits (versions, issue numbers, dates) vary between similar titles
+fn is_variable(token: &str) -> bool {
+    token.chars().any(|c| c.is_ascii_digit())
+}
+
+fn key_of(token: &str) -> String {
+    if is_variable(token) {
+        "#".to_owned()
+    } else {
+        token.to_lowercase()
+    }
+}
+
+/// Build a regex from titles sharing a prefix: tokens shared by all titles are kept,
+/// others become `\S+`, and it ends at the last shared token
+fn template(titles: &[&str]) -> Option<String> {
+    let tokens: Vec<Vec<&str>> = titles
+        .iter()
+        .map(|t| t.split_whitespace().collect())
+        .collect();
+    let len = tokens.iter().map(Vec::len).min()?;
+
+    let mut parts = vec![];
+    let mut last_literal = None;
+    for i in 0..len {
+        let first = tokens[0][i];
+        if !is_variable(first) && tokens.iter().all(|t| t[i].eq_ignore_ascii_case(first)) {
+            parts.push(regex::escape(first));
+            last_literal = Some(i);
+        } else {
+            parts.push(r"\S+".to_owned());
+        }
+    }
+    parts.truncate(last_literal? + 1);
+
+    let mut re = format!("^{}", parts.join(" "));
+    if re.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+        re.push_str(r"\b");
+    }
+    Some(re)
+}
+
+/// Cluster titles by their first one or two words and propose a regex for each cluster
+pub fn suggest(titles: &[&str], min_count: usize, samples: usize) -> Vec<Suggestion> {
+    let mut clusters = BTreeMap::<Vec<String>, Vec<&str>>::new();
+    for t in titles {
+        let keys: Vec<String> = t.split_whitespace().take(2).map(key_of).collect();
+        for n in 1..=keys.len() {
+            clusters.entry(keys[..n].to_vec()).or_default().push(t);
+        }
+    }
+
+    let mut ret: Vec<Suggestion> = vec![];
+    for (_, members) in clusters {
+        if members.len() < min_count {
+            continue;
+        }
+        let Some(re) = template(&members) else {
+            continue;
+        };
+        if ret.iter().any(|s| s.regex == re) {
+            continue;
+        }
+        let Ok(compiled) = RegexBuilder::new(&re).case_insensitive(true).build() else {
+            continue;
+        };
+        let matched: Vec<&str> = titles
+            .iter()
+            .copied()
+            .filter(|t| compiled.is_match(t))
+            .collect();
+        ret.push(Suggestion {
+            regex: re,
+            count: matched.len(),
+            samples: matched
+                .into_iter()
+                .take(samples)
+                .map(ToOwned::to_owned)
+                .collect(),
+        });
+    }
+
+    ret.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.regex.cmp(&b.regex)));
+    ret
+}
@@ -0,0 +1,454 @@
+//! How requests reach GitHub: over HTTP, recorded to fixtures, or replayed from them
+//!
+//! Every API call in this crate goes through [`Client`], which hands a [`Request`] to a
+//! [`Transport`]. Fixtures are JSON files, one per request, named in the order of requests:
+//!
+//! ```json
+//! {
+//!   "request": { "method": "GET", "url": "https://api.github.com/notifications?page=2" },
+//!   "response": { "status": 200, "headers": [["link", "<...>; rel=\"prev\""]], "body": [] }
+//! }
+//! ```
+//!
+//! JSON response bodies are stored as JSON, other bodies as a string.
+//! Request headers (including `Authorization`) are never recorded.
+//!
+//! For scenarios too long to record, [`mock::MockTransport`] fakes the API in memory.
+//!
+//! Each request is traced at the debug level, as a `request` span with the method, url, status,
+//! latency and how many times it was retried after hitting the rate limit, followed by a
+//! `response` event with the rate-limit headers. Request headers are never logged.
+
+pub mod mock;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::Instrument;
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    /// absolute, including the query
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    url: String,
+    body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, headers: &[(String, String)], url: &str, body: String) -> Result<Self> {
+        let mut map = HeaderMap::new();
+        for (k, v) in headers {
+            map.append(
+                HeaderName::from_bytes(k.as_bytes())?,
+                HeaderValue::from_str(v)?,
+            );
+        }
+        Ok(Self {
+            status: StatusCode::from_u16(status)?,
+            headers: map,
+            url: url.to_owned(),
+            body,
+        })
+    }
+
+    pub const fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub const fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn text(self) -> String {
+        self.body
+    }
+}
+
+/// Sends requests somewhere and returns responses
+pub trait Transport: Send + Sync {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        (**self).send(req)
+    }
+}
+
+/// A handle to a transport, with a request builder in the style of `reqwest::Client`
+#[derive(Clone)]
+pub struct Client {
+    transport: Arc<dyn Transport>,
+    /// requests answered with `Retry-After`, by method and url, to count retries in logs
+    rate_limited: Arc<Mutex<HashMap<(Method, String), u32>>>,
+}
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client").finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            rate_limited: Arc::default(),
+        }
+    }
+
+    /// Times the same request was rate limited before `resp`
+    fn retries(&self, req: &Request, resp: &Response) -> u32 {
+        let key = (req.method.clone(), req.url.clone());
+        let mut rate_limited = self.rate_limited.lock().unwrap();
+        if !resp.status.is_success() && resp.headers.contains_key("retry-after") {
+            let n = rate_limited.entry(key).or_default();
+            *n += 1;
+            *n - 1
+        } else {
+            rate_limited.remove(&key).unwrap_or(0)
+        }
+    }
+
+    pub fn request(&self, method: Method, url: impl AsRef<str>) -> RequestBuilder {
+        RequestBuilder {
+            client: self.clone(),
+            req: Request {
+                method,
+                url: url.as_ref().to_owned(),
+                headers: vec![],
+                body: None,
+            },
+        }
+    }
+
+    pub fn get(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn head(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    pub fn put(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+}
+
+#[derive(Clone)]
+#[must_use = "requests are only made by `send`"]
+pub struct RequestBuilder {
+    client: Client,
+    req: Request,
+}
+
+impl RequestBuilder {
+    /// Append query parameters to the url
+    pub fn query<K: AsRef<str>, V: AsRef<str>>(mut self, params: &[(K, V)]) -> Self {
+        if let Ok(mut url) = Url::parse(&self.req.url) {
+            url.query_pairs_mut()
+                .extend_pairs(params.iter().map(|(k, v)| (k.as_ref(), v.as_ref())));
+            self.req.url = url.into();
+        }
+        self
+    }
+
+    pub fn header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.req
+            .headers
+            .push((name.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.req.body = Some(body.into());
+        self
+    }
+
+    pub async fn send(self) -> Result<Response> {
+        let span = tracing::debug_span!(
+            "request",
+            method = %self.req.method,
+            url = %self.req.url,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+            retries = tracing::field::Empty,
+        );
+        let start = Instant::now();
+        let resp = self
+            .client
+            .transport
+            .send(self.req.clone())
+            .instrument(span.clone())
+            .await;
+        span.record(
+            "latency_ms",
+            u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        );
+
+        match &resp {
+            Ok(r) => {
+                let header = |k: &str| r.headers.get(k).and_then(|v| v.to_str().ok());
+                span.record("status", r.status.as_u16());
+                span.record("retries", self.client.retries(&self.req, r));
+                tracing::debug!(
+                    parent: &span,
+                    ratelimit_remaining = header("x-ratelimit-remaining"),
+                    ratelimit_reset = header("x-ratelimit-reset"),
+                    retry_after = header("retry-after"),
+                    "response"
+                );
+            }
+            Err(e) => tracing::debug!(parent: &span, error = %e, "request failed"),
+        }
+        resp
+    }
+}
+
+/// Sends requests to GitHub
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    /// `client` is expected to send the `Authorization` header by default
+    pub const fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn with_token(token: &str) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("token {}", token))?,
+        );
+        let client = reqwest::Client::builder()
+            .user_agent("GitHub Notification Filter (by equal-l2)")
+            .default_headers(headers)
+            .pool_idle_timeout(std::time::Duration::from_secs(30)) // ++ritual++ for retrying
+            .http2_prior_knowledge()
+            .https_only(true)
+            .build()?;
+        Ok(Self::new(client))
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let mut r = self.client.request(req.method, &req.url);
+            for (k, v) in &req.headers {
+                r = r.header(k.as_str(), v.as_str());
+            }
+            if let Some(b) = req.body {
+                r = r.body(b);
+            }
+            let resp = r.send().await?;
+            Ok(Response {
+                status: resp.status(),
+                headers: resp.headers().clone(),
+                url: resp.url().to_string(),
+                body: resp.text().await?,
+            })
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: Value,
+}
+
+fn to_value(body: &str) -> Value {
+    serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_owned()))
+}
+
+fn from_value(body: &Value) -> String {
+    match body {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Passes requests to another transport, saving each request/response pair as a fixture
+pub struct RecordTransport<T> {
+    inner: T,
+    dir: PathBuf,
+    seq: AtomicUsize,
+}
+
+impl<T: Transport> RecordTransport<T> {
+    pub fn new(inner: T, dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self {
+            inner,
+            dir: dir.to_owned(),
+            seq: AtomicUsize::new(1),
+        })
+    }
+
+    fn save(&self, req: &Request, resp: &Response) -> Result<()> {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        let slug: String = req
+            .url
+            .trim_start_matches("https://api.github.com/")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .take(60)
+            .collect();
+        let path = self
+            .dir
+            .join(format!("{:04}-{}-{}.json", seq, req.method, slug));
+        let fixture = Fixture {
+            request: RecordedRequest {
+                method: req.method.to_string(),
+                url: req.url.clone(),
+                body: req.body.as_deref().map(to_value),
+            },
+            response: RecordedResponse {
+                status: resp.status.as_u16(),
+                headers: resp
+                    .headers
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_owned())))
+                    .collect(),
+                body: to_value(&resp.body),
+            },
+        };
+        std::fs::write(&path, serde_json::to_string_pretty(&fixture)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl<T: Transport> Transport for RecordTransport<T> {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let resp = self.inner.send(req.clone()).await?;
+            self.save(&req, &resp)?;
+            Ok(resp)
+        })
+    }
+}
+
+/// Serves responses from fixtures, without network
+///
+/// A request gets the first unused fixture with the same method and url.
+/// When all of them are used, the last one is served again (e.g. for polling).
+pub struct ReplayTransport {
+    fixtures: Vec<(String, Fixture)>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayTransport {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        paths.sort();
+
+        let mut fixtures = vec![];
+        for p in paths {
+            let text = std::fs::read_to_string(&p)
+                .with_context(|| format!("Failed to read {}", p.display()))?;
+            let f = serde_json::from_str(&text)
+                .with_context(|| format!("Malformed fixture {}", p.display()))?;
+            fixtures.push((p.display().to_string(), f));
+        }
+        let used = Mutex::new(vec![false; fixtures.len()]);
+        Ok(Self { fixtures, used })
+    }
+
+    /// Fixtures which have not been served, e.g. to check that a test made every request
+    pub fn unused(&self) -> Vec<&str> {
+        let used = self.used.lock().unwrap();
+        self.fixtures
+            .iter()
+            .zip(used.iter())
+            .filter(|(_, &u)| !u)
+            .map(|((name, _), _)| name.as_str())
+            .collect()
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        let method = req.method.to_string();
+        let found = {
+            let mut used = self.used.lock().unwrap();
+            let matching: Vec<usize> = self
+                .fixtures
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, f))| f.request.method == method && f.request.url == req.url)
+                .map(|(i, _)| i)
+                .collect();
+            let i = matching
+                .iter()
+                .copied()
+                .find(|&i| !used[i])
+                .or_else(|| matching.last().copied());
+            if let Some(i) = i {
+                used[i] = true;
+            }
+            i
+        };
+        Box::pin(async move {
+            let i = found.ok_or_else(|| anyhow!("No fixture for {} {}", method, req.url))?;
+            let r = &self.fixtures[i].1.response;
+            Response::new(r.status, &r.headers, &req.url, from_value(&r.body))
+        })
+    }
+}
@@ -0,0 +1,433 @@
+//! An in-memory fake of the notifications API, for running without network
+//!
+//! It is seeded from a JSON file:
+//!
+//! ```json
+//! {
+//!   "notifications": [ { "id": "1", "repository": { "full_name": "a/b" }, "subject": { ... }, ... } ],
+//!   "subjects": { "https://api.github.com/repos/a/b/issues/1": { "state": "open", ... } },
+//!   "watched": ["a/b"],
+//!   "per_page": 50,
+//!   "rate_limit": { "every": 10, "retry_after": 1 }
+//! }
+//! ```
+//!
+//! `notifications` are in the format of `GET /notifications`, with optional `unread` (default
+//! `true`), `subscribed` (default `true`) and `ignored` (default `false`). `subjects` are served
+//! as they are for `GET` on their url (issues, pull requests, commits and comments). With
+//! `rate_limit`, every `every`th request is answered with 403 and `Retry-After`, or without it
+//! (as when the hourly limit is exhausted) if `retry_after` is not given. With
+//! `"unauthorized": true`, every request is answered with 401 as for a wrong token.
+//!
+//! Changes (marking as read or done, unsubscribing, unwatching, ...) live until the process exits.
+//! A thread marked as done (`DELETE /notifications/threads/{id}`) is gone, as on GitHub.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{Request, Response, Transport};
+
+const API: &str = "https://api.github.com";
+
+#[derive(Debug, Deserialize)]
+pub struct Seed {
+    pub notifications: Vec<Value>,
+    #[serde(default)]
+    pub subjects: HashMap<String, Value>,
+    #[serde(default)]
+    pub watched: Vec<String>,
+    #[serde(default = "default_per_page")]
+    pub per_page: usize,
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default)]
+    pub unauthorized: bool,
+}
+
+const fn default_per_page() -> usize {
+    50
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RateLimit {
+    pub every: usize,
+    pub retry_after: Option<u64>,
+}
+
+/// State of a thread in the mock
+#[derive(Clone, Debug)]
+pub struct MockThread {
+    pub id: String,
+    pub repo: String,
+    pub updated_at: String,
+    pub unread: bool,
+    pub subscribed: bool,
+    pub ignored: bool,
+    json: Value,
+}
+
+impl MockThread {
+    fn to_json(&self) -> Value {
+        let mut v = self.json.clone();
+        v["unread"] = json!(self.unread);
+        v
+    }
+
+    fn subscription_json(&self) -> Value {
+        json!({
+            "subscribed": self.subscribed,
+            "ignored": self.ignored,
+            "thread_url": format!("{}/notifications/threads/{}", API, self.id),
+        })
+    }
+}
+
+struct State {
+    threads: Vec<MockThread>,
+    subjects: HashMap<String, Value>,
+    watched: Vec<String>,
+    ignored_repos: Vec<String>,
+    per_page: usize,
+    rate_limit: Option<RateLimit>,
+    unauthorized: bool,
+    requests: usize,
+    /// bumped on every change, served as `Last-Modified`
+    last_modified: String,
+}
+
+impl State {
+    fn touch(&mut self) {
+        self.last_modified = chrono::Utc::now().to_rfc2822();
+    }
+}
+
+pub struct MockTransport {
+    state: Mutex<State>,
+}
+
+impl MockTransport {
+    pub fn new(seed: Seed) -> Result<Self> {
+        let threads = seed
+            .notifications
+            .into_iter()
+            .map(|n| {
+                let str_of = |v: &Value| v.as_str().map(ToOwned::to_owned);
+                let id = str_of(&n["id"]).ok_or_else(|| anyhow!("notification without id"))?;
+                Ok(MockThread {
+                    repo: str_of(&n["repository"]["full_name"]).unwrap_or_default(),
+                    updated_at: str_of(&n["updated_at"]).unwrap_or_default(),
+                    unread: n["unread"].as_bool().unwrap_or(true),
+                    subscribed: n["subscribed"].as_bool().unwrap_or(true),
+                    ignored: n["ignored"].as_bool().unwrap_or(false),
+                    id,
+                    json: n,
+                })
+            })
+            .collect::<Result<Vec<MockThread>>>()?;
+        // the latest update, so that runs seeded alike agree on it
+        let last_modified = threads
+            .iter()
+            .filter_map(|t| chrono::DateTime::parse_from_rfc3339(&t.updated_at).ok())
+            .max()
+            .map_or_else(chrono::Utc::now, |t| t.with_timezone(&chrono::Utc))
+            .to_rfc2822();
+        Ok(Self {
+            state: Mutex::new(State {
+                threads,
+                subjects: seed.subjects,
+                watched: seed.watched,
+                ignored_repos: vec![],
+                per_page: seed.per_page.max(1),
+                rate_limit: seed.rate_limit,
+                unauthorized: seed.unauthorized,
+                requests: 0,
+                last_modified,
+            }),
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let seed = serde_json::from_str(&text)
+            .with_context(|| format!("Malformed seed {}", path.display()))?;
+        Self::new(seed)
+    }
+
+    pub fn thread(&self, id: &str) -> Option<MockThread> {
+        let state = self.state.lock().unwrap();
+        state.threads.iter().find(|t| t.id == id).cloned()
+    }
+
+    pub fn watched(&self) -> Vec<String> {
+        self.state.lock().unwrap().watched.clone()
+    }
+
+    pub fn ignored_repos(&self) -> Vec<String> {
+        self.state.lock().unwrap().ignored_repos.clone()
+    }
+
+    /// Number of requests served, including rate-limited ones
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+    fn handle(&self, req: &Request) -> Result<Response> {
+        let url = Url::parse(&req.url)?;
+        // ordered, so that `Link` lists the parameters the same way on every run
+        let query: BTreeMap<String, String> = url.query_pairs().into_owned().collect();
+        let segments: Vec<&str> = url.path_segments().map_or_else(Vec::new, Iterator::collect);
+        let header = |name: &str| {
+            req.headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        let body: Value = req
+            .body
+            .as_deref()
+            .and_then(|b| serde_json::from_str(b).ok())
+            .unwrap_or(Value::Null);
+
+        let mut state = self.state.lock().unwrap();
+        state.requests += 1;
+        if state.unauthorized {
+            return respond(401, &[], &req.url, &json!({ "message": "Bad credentials" }));
+        }
+        if let Some(r) = state.rate_limit {
+            if r.every > 0 && state.requests.is_multiple_of(r.every) {
+                let reset = chrono::Utc::now().timestamp() + 3600;
+                let mut headers = vec![
+                    ("x-ratelimit-remaining".to_owned(), "0".to_owned()),
+                    ("x-ratelimit-reset".to_owned(), reset.to_string()),
+                ];
+                if let Some(t) = r.retry_after {
+                    headers.push(("retry-after".to_owned(), t.to_string()));
+                }
+                return respond(
+                    403,
+                    &headers,
+                    &req.url,
+                    &json!({ "message": "API rate limit exceeded" }),
+                );
+            }
+        }
+
+        let m = &req.method;
+        match segments.as_slice() {
+            ["notifications"] if m == Method::GET || m == Method::HEAD => {
+                list(&state, None, &query, header("if-modified-since"), req)
+            }
+            ["repos", o, r, "notifications"] if m == Method::GET || m == Method::HEAD => {
+                list(&state, Some(&format!("{}/{}", o, r)), &query, None, req)
+            }
+            ["notifications"] if m == Method::PUT => mark_all(&mut state, None, &body, req),
+            ["repos", o, r, "notifications"] if m == Method::PUT => {
+                mark_all(&mut state, Some(&format!("{}/{}", o, r)), &body, req)
+            }
+            ["notifications", "threads", id, rest @ ..] => {
+                let Some(i) = state.threads.iter().position(|t| t.id == *id) else {
+                    return not_found(&req.url);
+                };
+                match (m.clone(), rest) {
+                    (Method::GET, []) => respond(200, &[], &req.url, &state.threads[i].to_json()),
+                    (Method::PATCH, []) => {
+                        state.threads[i].unread = false;
+                        state.touch();
+                        respond(205, &[], &req.url, &Value::Null)
+                    }
+                    // mark as done
+                    (Method::DELETE, []) => {
+                        state.threads.remove(i);
+                        state.touch();
+                        respond(204, &[], &req.url, &Value::Null)
+                    }
+                    (Method::GET, ["subscription"]) => {
+                        respond(200, &[], &req.url, &state.threads[i].subscription_json())
+                    }
+                    (Method::PUT, ["subscription"]) => {
+                        let t = &mut state.threads[i];
+                        t.ignored = body["ignored"].as_bool().unwrap_or(false);
+                        t.subscribed = !t.ignored;
+                        let v = t.subscription_json();
+                        respond(200, &[], &req.url, &v)
+                    }
+                    (Method::DELETE, ["subscription"]) => {
+                        state.threads[i].subscribed = false;
+                        respond(204, &[], &req.url, &Value::Null)
+                    }
+                    _ => not_found(&req.url),
+                }
+            }
+            ["repos", o, r, "subscription"] => {
+                let repo = format!("{}/{}", o, r);
+                match m.clone() {
+                    Method::PUT => {
+                        state.watched.retain(|w| w != &repo);
+                        state.ignored_repos.retain(|w| w != &repo);
+                        let ignored = body["ignored"].as_bool().unwrap_or(false);
+                        let subscribed = body["subscribed"].as_bool().unwrap_or(false);
+                        if ignored {
+                            state.ignored_repos.push(repo);
+                        } else if subscribed {
+                            state.watched.push(repo);
+                        }
+                        let v = json!({ "subscribed": subscribed && !ignored, "ignored": ignored });
+                        respond(200, &[], &req.url, &v)
+                    }
+                    Method::DELETE => {
+                        state.watched.retain(|w| w != &repo);
+                        state.ignored_repos.retain(|w| w != &repo);
+                        respond(204, &[], &req.url, &Value::Null)
+                    }
+                    _ => not_found(&req.url),
+                }
+            }
+            ["user"] if m == Method::GET => respond(
+                200,
+                &[(
+                    "x-oauth-scopes".to_owned(),
+                    "notifications, repo".to_owned(),
+                )],
+                &req.url,
+                &json!({ "login": "mock" }),
+            ),
+            ["user", "subscriptions"] if m == Method::GET => {
+                let repos: Vec<Value> = state
+                    .watched
+                    .iter()
+                    .map(|r| json!({ "full_name": r }))
+                    .collect();
+                respond(200, &[], &req.url, &Value::Array(repos))
+            }
+            _ if m == Method::GET => {
+                let key = format!("{}{}", API, url.path());
+                state
+                    .subjects
+                    .get(&key)
+                    .map_or_else(|| not_found(&req.url), |v| respond(200, &[], &req.url, v))
+            }
+            _ => not_found(&req.url),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(futures::future::ready(self.handle(&req)))
+    }
+}
+
+fn respond(status: u16, headers: &[(String, String)], url: &str, body: &Value) -> Result<Response> {
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    Response::new(status, headers, url, body)
+}
+
+fn not_found(url: &str) -> Result<Response> {
+    respond(404, &[], url, &json!({ "message": "Not Found" }))
+}
+
+/// `GET /notifications` (or of a repository): unread threads, paginated with `Link`
+fn list(
+    state: &State,
+    repo: Option<&str>,
+    query: &BTreeMap<String, String>,
+    if_modified_since: Option<&str>,
+    req: &Request,
+) -> Result<Response> {
+    if if_modified_since == Some(state.last_modified.as_str()) {
+        return respond(304, &[], &req.url, &Value::Null);
+    }
+
+    let before = query.get("before");
+    let threads: Vec<&MockThread> = state
+        .threads
+        .iter()
+        .filter(|t| t.unread)
+        .filter(|t| repo.is_none_or(|r| t.repo == r))
+        .filter(|t| before.is_none_or(|b| t.updated_at.as_str() < b.as_str()))
+        .collect();
+
+    let per_page = query
+        .get("per_page")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(state.per_page)
+        .max(1);
+    let last = threads.len().div_ceil(per_page).max(1);
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(1usize)
+        .clamp(1, last + 1);
+
+    let mut headers = vec![
+        ("last-modified".to_owned(), state.last_modified.clone()),
+        ("x-poll-interval".to_owned(), "60".to_owned()),
+    ];
+    if last > 1 {
+        // other parameters first, GitHub puts `page` last
+        let base = Url::parse(&req.url)?;
+        let others: Vec<String> = query
+            .iter()
+            .filter(|(k, _)| *k != "page")
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        let link_to = |p: usize| {
+            let mut u = format!("{}{}?", API, base.path());
+            for o in &others {
+                u.push_str(o);
+                u.push('&');
+            }
+            format!("{}page={}", u, p)
+        };
+        let mut links = vec![];
+        if page < last {
+            links.push(format!("<{}>; rel=\"next\"", link_to(page + 1)));
+            links.push(format!("<{}>; rel=\"last\"", link_to(last)));
+        }
+        if page > 1 {
+            links.push(format!("<{}>; rel=\"first\"", link_to(1)));
+            links.push(format!("<{}>; rel=\"prev\"", link_to(page - 1)));
+        }
+        headers.push(("link".to_owned(), links.join(", ")));
+    }
+
+    if req.method == Method::HEAD {
+        return respond(200, &headers, &req.url, &Value::Null);
+    }
+    let body: Vec<Value> = threads
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .map(|t| t.to_json())
+        .collect();
+    respond(200, &headers, &req.url, &Value::Array(body))
+}
+
+/// `PUT /notifications` (or of a repository): mark threads updated before `last_read_at` as read
+fn mark_all(
+    state: &mut State,
+    repo: Option<&str>,
+    body: &Value,
+    req: &Request,
+) -> Result<Response> {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let until = body["last_read_at"].as_str().unwrap_or(&now).to_owned();
+    for t in &mut state.threads {
+        if repo.is_none_or(|r| t.repo == r) && t.updated_at < until {
+            t.unread = false;
+        }
+    }
+    state.touch();
+    respond(205, &[], &req.url, &Value::Null)
+}
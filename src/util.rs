@@ -1,247 +1,551 @@
 use anyhow::{anyhow, Result};
-use futures::future::try_join_all;
-use regex::RegexSet;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use futures::future::{join_all, try_join_all};
+use futures::Future;
+use regex::Regex;
 
-use crate::subscription::{SubjectState, Subscription, ThreadID};
+use crate::config;
+use crate::journal::{self, Journal};
+use crate::rules::{self, Rule, RuleSet};
+use crate::subscription::{SubjectDetail, SubjectState, Subscription};
+use crate::transport::Client;
 use crate::SubjectType;
 
+/// Which notifications to select, and in which order
+///
+/// The default selects everything in the order of the API.
+#[derive(Debug, Default)]
 pub struct Filters {
-    regex: Option<RegexSet>,
-    kind: Option<SubjectType>,
-    count: Option<usize>,
+    /// title rules, e.g. from `~/.ghnf/filters`
+    pub rules: Option<RuleSet>,
+    pub kind: Option<SubjectType>,
+    /// the reason of the notification, e.g. `review_requested`
+    pub reason: Option<String>,
+    /// repositories matching the glob, see `rules::glob_to_regex`
+    pub repo: Option<Regex>,
+    pub detail: DetailFilter,
+    pub state: StateFilter,
+    pub commits: CommitPolicy,
+    pub sort: Option<Sort>,
+    /// take the first `count` after sorting
+    pub count: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Updated,
+    Repo,
+    Type,
+    Title,
+    Reason,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "updated" => Ok(Self::Updated),
+            "repo" => Ok(Self::Repo),
+            "type" => Ok(Self::Type),
+            "title" => Ok(Self::Title),
+            "reason" => Ok(Self::Reason),
+            _ => Err(format!("unknown sort key {:?}", s)),
+        }
+    }
+}
+
+impl SortKey {
+    pub fn compare(self, a: &Subscription, b: &Subscription) -> std::cmp::Ordering {
+        match self {
+            Self::Updated => a.updated_at.cmp(&b.updated_at),
+            Self::Repo => a.repo_name.cmp(&b.repo_name),
+            Self::Type => a.subject.r#type.cmp(&b.subject.r#type),
+            Self::Title => a.subject.title.cmp(&b.subject.title),
+            Self::Reason => a.reason.cmp(&b.reason),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Sort {
+    pub key: SortKey,
+    pub desc: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroupKey {
+    Repo,
+    Type,
+    Reason,
+}
+
+impl std::str::FromStr for GroupKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "repo" => Ok(Self::Repo),
+            "type" => Ok(Self::Type),
+            "reason" => Ok(Self::Reason),
+            _ => Err(format!("unknown group key {:?}", s)),
+        }
+    }
+}
+
+impl GroupKey {
+    pub fn key_of(self, s: &Subscription) -> String {
+        match self {
+            Self::Repo => s.repo_name.clone(),
+            Self::Type => s.subject.r#type.to_string(),
+            Self::Reason => s.reason.clone(),
+        }
+    }
+}
+
+/// Filter on the subject state, `merged` and `closed-unmerged` only apply to pull requests
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StateFilter {
+    Open,
+    Closed,
+    Merged,
+    ClosedUnmerged,
+    #[default]
+    Any,
+}
+
+impl std::str::FromStr for StateFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "closed" => Ok(Self::Closed),
+            "merged" => Ok(Self::Merged),
+            "closed-unmerged" => Ok(Self::ClosedUnmerged),
+            "any" => Ok(Self::Any),
+            _ => Err(format!("unknown state {:?}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for StateFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Open => write!(f, "open"),
+            Self::Closed => write!(f, "closed"),
+            Self::Merged => write!(f, "merged"),
+            Self::ClosedUnmerged => write!(f, "closed-unmerged"),
+            Self::Any => write!(f, "any"),
+        }
+    }
+}
+
+impl StateFilter {
+    pub fn is_match(self, d: &SubjectDetail) -> bool {
+        let merged = d.merged == Some(true);
+        match self {
+            Self::Open => d.state == Some(SubjectState::Open),
+            Self::Closed => d.state == Some(SubjectState::Closed),
+            Self::Merged => merged,
+            Self::ClosedUnmerged => d.state == Some(SubjectState::Closed) && !merged,
+            Self::Any => true,
+        }
+    }
+}
+
+/// How to treat commits, which don't have state, when filtering by state
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CommitPolicy {
+    #[default]
+    Include,
+    Exclude,
+}
+
+impl std::str::FromStr for CommitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(Self::Include),
+            "exclude" => Ok(Self::Exclude),
+            _ => Err(format!("unknown commit policy {:?}", s)),
+        }
+    }
+}
+
+/// Filters on the subject detail, which needs a request per thread
+#[derive(Debug, Default)]
+pub struct DetailFilter {
+    pub author: Option<String>,
+    pub labels: Vec<String>,
+    pub base: Option<String>,
+    pub draft: bool,
+    pub merged: bool,
+    pub not_planned: bool,
+}
+
+impl DetailFilter {
+    pub const fn is_empty(&self) -> bool {
+        self.author.is_none()
+            && self.labels.is_empty()
+            && self.base.is_none()
+            && !self.draft
+            && !self.merged
+            && !self.not_planned
+    }
+
+    pub fn is_match(&self, d: &SubjectDetail) -> bool {
+        self.author.as_ref().is_none_or(|a| {
+            d.user
+                .as_ref()
+                .is_some_and(|u| u.login.eq_ignore_ascii_case(a))
+        }) && self
+            .labels
+            .iter()
+            .all(|l| d.labels.iter().any(|dl| dl.name.eq_ignore_ascii_case(l)))
+            && self
+                .base
+                .as_ref()
+                .is_none_or(|b| d.base.as_ref().is_some_and(|db| &db.r#ref == b))
+            && (!self.draft || d.draft == Some(true))
+            && (!self.merged || d.merged == Some(true))
+            && (!self.not_planned || d.is_not_planned())
+    }
 }
 
 impl Filters {
-    pub fn new(m: &clap::ArgMatches<'_>, with_default_regex: bool) -> Result<Self> {
-        Ok(Self {
-            regex: {
-                if let Some(i) = m.value_of("filter") {
-                    Some(RegexSet::new(&[i])?)
-                } else if with_default_regex {
-                    Some(compile_regex()?)
-                } else {
-                    None
-                }
-            },
-            kind: m.value_of("kind").and_then(|v| match v {
-                "commit" => Some(SubjectType::Commit),
-                "issue" => Some(SubjectType::Issue),
-                "pr" => Some(SubjectType::PullRequest),
-                _ => unreachable!(),
-            }),
-            count: m.value_of("count").map(|v| {
-                v.parse().unwrap_or_else(|_| {
-                    eprintln!("Invalid argument for <count>, expected integer");
-                    std::process::exit(1)
-                })
-            }),
-        })
-    }
-}
-
-const CHUNK_SIZE: usize = 64;
-
-pub fn read_config(filename: &str) -> Result<String> {
-    let path = dirs::home_dir()
-        .ok_or_else(|| anyhow!("Failed to read ~/"))?
-        .join(".ghnf")
-        .join(filename);
-    std::fs::read_to_string(path).map_err(Into::into)
-}
-
-pub fn compile_regex() -> Result<RegexSet> {
-    regex::RegexSetBuilder::new(
-        read_config("filters")
-            .expect("Failed to read filters from ~/.ghnf/filters")
-            .split('\n')
-            .filter(|s| !s.is_empty()),
-    )
-    .case_insensitive(true)
-    .build()
-    .map_err(Into::into)
-}
-
-pub fn create_client() -> Result<Client> {
-    let token = read_config("token")
-        .expect("Failed to read GitHub token from ~/.ghnf/token")
-        .split('\n')
-        .next()
-        .ok_or_else(|| anyhow!("Malformed GitHub Personal Access Token"))?
-        .to_owned();
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::AUTHORIZATION,
-        reqwest::header::HeaderValue::from_str(&format!("token {}", token))?,
-    );
-    Client::builder()
-        .user_agent("GitHub Notification Filter (by equal-l2)")
-        .default_headers(headers)
-        .pool_idle_timeout(std::time::Duration::from_secs(30)) // ++ritual++ for retrying
-        .http2_prior_knowledge()
-        .https_only(true)
-        .build()
-        .map_err(Into::into)
-}
-
-pub fn load_ignored() -> Result<Vec<ThreadID>> {
-    // `ignore` is optional, return empty vec when not found
-    read_config("ignore")
-        .or_else(|e| {
-            if let Some(i) = e.downcast_ref::<std::io::Error>() {
-                if matches!(i.kind(), std::io::ErrorKind::NotFound) {
-                    return Ok("".into());
-                }
+    /// The rule which selected `s`, if filtered by rules
+    pub fn matching_rule(&self, s: &Subscription) -> Option<&Rule> {
+        self.rules.as_ref().and_then(|r| r.matching_rule(s))
+    }
+
+    /// Reload `~/.ghnf/filters` if it has been modified, returning whether it was reloaded
+    pub fn reload_rules(&mut self) -> Result<bool> {
+        match &self.rules {
+            Some(r) if r.is_stale() => {
+                self.rules = Some(rules::load_rules()?);
+                Ok(true)
             }
-            Err(e)
-        })?
-        .split('\n')
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<ThreadID>().map_err(Into::into))
-        .collect()
+            _ => Ok(false),
+        }
+    }
 }
 
+pub const CHUNK_SIZE: usize = 64;
+
 pub fn filter_ignored(ss: Vec<Subscription>) -> Result<Vec<Subscription>> {
-    let ignore = load_ignored().expect("Failed to read GitHub token from ~/.ghnf/ignored");
+    let ignore = config::load_ignored()?;
     Ok(ss
         .into_iter()
         .filter(|s| !ignore.contains(&s.thread_id))
         .collect())
 }
 
-pub async fn filter_by_subject_state(
-    ss: Vec<Subscription>,
-    state: SubjectState,
-    c: &Client,
-) -> Result<Vec<Subscription>> {
+/// Filter subscriptions with an async predicate, running `CHUNK_SIZE` requests at once
+async fn filter_chunked<F, Fut>(ss: Vec<Subscription>, f: F) -> Result<Vec<Subscription>>
+where
+    F: Fn(Subscription) -> Fut,
+    Fut: Future<Output = Result<Option<Subscription>>>,
+{
     let mut futs = Vec::with_capacity(CHUNK_SIZE);
     let mut ret = vec![];
     for s in ss {
-        futs.push(async {
-            match s.subject.r#type {
-                // Commits don't have state but we want to handle them
-                SubjectType::Commit => Ok(Some(s)),
-                // Just ignore discussions at this moment (until GitHub fixes FIXME!)
-                SubjectType::Discussion => Ok(None),
-                // Now look at the state
-                _ => match s.subject_state(c).await? {
-                    Some(i) if i == state => Ok(Some(s)),
-                    _ => Ok(None),
-                },
-            }
-        });
+        futs.push(f(s));
         if futs.len() >= CHUNK_SIZE {
-            //eprintln!("join!");
             let r: Result<_> = try_join_all(futs.drain(..)).await;
             ret.extend(r?.into_iter().flatten());
         }
     }
     {
-        //eprintln!("final join!");
         let r: Result<_> = try_join_all(futs).await;
         ret.extend(r?.into_iter().flatten());
     }
     Ok(ret)
 }
 
-pub async fn unsubscribe_all(ss: Vec<Subscription>, dry: bool, c: &Client) -> Result<()> {
-    if ss.is_empty() {
-        println!("No notification matched");
-        return Ok(());
+pub async fn filter_by_subject_state(
+    ss: Vec<Subscription>,
+    state: StateFilter,
+    commits: CommitPolicy,
+    c: &Client,
+) -> Result<Vec<Subscription>> {
+    if state == StateFilter::Any {
+        // no need to fetch the detail
+        return Ok(ss
+            .into_iter()
+            .filter(|s| commits == CommitPolicy::Include || s.subject.r#type != SubjectType::Commit)
+            .collect());
     }
 
-    if dry {
-        println!("\nFollowing threads are going to be unsubscribed:");
-        for s in &ss {
-            println!("{}", s);
+    filter_chunked(ss, |s| async {
+        match s.subject.r#type {
+            // Commits don't have state, follow the policy
+            SubjectType::Commit => Ok(Some(s).filter(|_| commits == CommitPolicy::Include)),
+            // Just ignore discussions at this moment (until GitHub fixes FIXME!)
+            SubjectType::Discussion => Ok(None),
+            // Now look at the state
+            _ => {
+                if state.is_match(s.subject_detail(c).await?) {
+                    Ok(Some(s))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    })
+    .await
+}
+
+pub async fn filter_by_subject_detail(
+    ss: Vec<Subscription>,
+    filt: &DetailFilter,
+    c: &Client,
+) -> Result<Vec<Subscription>> {
+    filter_chunked(ss, |s| async {
+        match s.subject.r#type {
+            // Discussions don't have detail (until GitHub fixes FIXME!)
+            SubjectType::Discussion => Ok(None),
+            _ => {
+                if filt.is_match(s.subject_detail(c).await?) {
+                    Ok(Some(s))
+                } else {
+                    Ok(None)
+                }
+            }
         }
-        return Ok(());
+    })
+    .await
+}
+
+/// Some threads failed while the others were handled, each failure is logged
+#[derive(Debug, thiserror::Error)]
+#[error("{failed} of {total} thread(s) failed")]
+pub struct PartialFailure {
+    pub failed: usize,
+    pub total: usize,
+}
+
+/// Collect the results of handling `total` threads
+///
+/// Failures are logged. When every thread failed, the first error is returned as it is.
+pub fn partial_result(errors: Vec<anyhow::Error>, total: usize) -> Result<()> {
+    for e in &errors {
+        tracing::warn!("{:#}", e);
     }
+    let failed = errors.len();
+    match errors.into_iter().next() {
+        None => Ok(()),
+        Some(e) if failed == total => Err(e),
+        Some(_) => Err(PartialFailure { failed, total }.into()),
+    }
+}
 
-    println!("Unsubscribing notifications...");
+/// Unsubscribe from and mark as read each of `ss`, recording them in the journal
+///
+/// A thread failing doesn't stop the others, see [`partial_result`].
+pub async fn unsubscribe_all(ss: Vec<Subscription>, filt: &Filters, c: &Client) -> Result<()> {
+    let journal = Journal::open()?;
+    let journal = &journal;
+    let total = ss.len();
     let mut futs = vec![];
+    let mut errors = vec![];
     for s in ss {
         futs.push(async move {
-            s.unsubscribe(c).await?;
-            s.mark_as_read(c).await?;
-            println!("Unsubscribed {}", s);
-            Result::<()>::Ok(())
+            let r = async {
+                s.unsubscribe(c).await?;
+                journal.record(
+                    &s,
+                    journal::Action::Unsubscribe,
+                    filt.matching_rule(&s).map(ToString::to_string),
+                )?;
+                s.mark_as_read(c).await
+            }
+            .await;
+            if r.is_ok() {
+                tracing::info!("Unsubscribed {}", s);
+            }
+            r.map_err(|e| e.context(format!("Failed to unsubscribe {}", s)))
         });
         if futs.len() >= CHUNK_SIZE {
-            try_join_all(futs.drain(..)).await?;
+            errors.extend(
+                join_all(futs.drain(..))
+                    .await
+                    .into_iter()
+                    .filter_map(Result::err),
+            );
         }
     }
-    try_join_all(futs).await?;
-    Ok(())
+    errors.extend(join_all(futs).await.into_iter().filter_map(Result::err));
+    partial_result(errors, total)
 }
 
-pub async fn fetch_filtered(filt: Filters, c: &Client) -> Result<Vec<Subscription>> {
-    println!("Fetching notifications...");
+pub async fn fetch_filtered(filt: &Filters, c: &Client) -> Result<Vec<Subscription>> {
+    tracing::info!("Fetching notifications...");
 
     let svec = Subscription::fetch_unread(c).await?;
-    println!(
+    tracing::info!(
         "Fetched {} notifications",
         svec.iter().map(Vec::len).sum::<usize>()
     );
 
-    let ss: Vec<Subscription> = if let Some(r) = filt.regex {
-        println!("Filtering notifications by regex...");
-        let mut futs = vec![];
-        for ss in svec {
-            futs.push(async {
-                ss.into_iter().flat_map(|s| {
-                    if r.is_match(&s.subject.title) {
-                        Some(s)
-                    } else {
-                        None
-                    }
-                })
-            });
-        }
-        futures::future::join_all::<Vec<_>>(futs)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
-    } else {
-        svec.into_iter().flatten().collect()
-    };
+    tracing::info!("Filtering notifications...");
+    apply_filters(svec.into_iter().flatten().collect(), filt, c).await
+}
+
+/// Apply rules, kind and subject detail filters (not state, see `filter_by_subject_state`)
+pub async fn apply_filters(
+    ss: Vec<Subscription>,
+    filt: &Filters,
+    c: &Client,
+) -> Result<Vec<Subscription>> {
+    let ss = ss.into_iter().filter(|s| {
+        filt.rules.as_ref().is_none_or(|r| r.is_match(s))
+            && filt.kind.is_none_or(|k| s.subject.r#type == k)
+            && filt.reason.as_ref().is_none_or(|r| &s.reason == r)
+            && filt.repo.as_ref().is_none_or(|r| r.is_match(&s.repo_name))
+    });
 
-    // filter by kind
-    let ss = if let Some(i) = filt.kind {
-        println!("Filtering notifications by kind...");
-        ss.into_iter().filter(|s| s.subject.r#type == i).collect()
+    if filt.detail.is_empty() {
+        Ok(ss.collect())
     } else {
-        ss
+        filter_by_subject_detail(ss.collect(), &filt.detail, c).await
+    }
+}
+
+/// Sort by `--sort` (ties are broken by the update time and the thread ID), then apply `-n`
+pub fn sort_and_take(mut ss: Vec<Subscription>, filt: &Filters) -> Vec<Subscription> {
+    if let Some(sort) = filt.sort {
+        ss.sort_by(|a, b| {
+            let o = sort
+                .key
+                .compare(a, b)
+                .then_with(|| a.updated_at.cmp(&b.updated_at))
+                .then_with(|| a.thread_id.cmp(&b.thread_id));
+            if sort.desc {
+                o.reverse()
+            } else {
+                o
+            }
+        });
+    }
+
+    if let Some(i) = filt.count {
+        ss.truncate(i);
+    }
+    ss
+}
+
+/// Split into groups sorted by the key, keeping the order inside each group
+pub fn group_by(ss: &[Subscription], key: GroupKey) -> Vec<(String, Vec<&Subscription>)> {
+    let mut groups = std::collections::BTreeMap::<_, Vec<_>>::new();
+    for s in ss {
+        groups.entry(key.key_of(s)).or_default().push(s);
+    }
+    groups.into_iter().collect()
+}
+
+/// Open `url` with `browser` (a command line, `{}` is replaced with the url or it is appended),
+/// or with the system default
+///
+/// `browser` is split into words like a POSIX shell does, so paths with spaces must be quoted,
+/// but it is not run by a shell: there is no expansion of variables, globs or `~`.
+pub fn open_url(url: &str, browser: Option<&str>) -> Result<()> {
+    let Some(cmd) = browser else {
+        return open::that(url)
+            .map(|_| ()) // discard ExitStatus
+            .map_err(Into::into);
     };
 
-    // handle count
-    let ss = if let Some(i) = filt.count {
-        ss.into_iter().take(i).collect()
+    let mut args = shell_words::split(cmd)
+        .map_err(|e| anyhow!("Failed to parse the browser command {:?}: {}", cmd, e))?;
+    if args.is_empty() {
+        return Err(anyhow!("empty browser command"));
+    }
+    if args.iter().any(|a| a.contains("{}")) {
+        for a in &mut args {
+            *a = a.replace("{}", url);
+        }
     } else {
-        ss
+        args.push(url.to_owned());
+    }
+    // don't wait, the browser may keep running
+    std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| anyhow!("Failed to run {:?}: {}", args[0], e))
+}
+
+/// Parse a duration like `30m`, `12h`, `3d` or `2w`
+pub fn parse_duration(s: &str) -> Result<chrono::Duration> {
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: i64 = num
+        .parse()
+        .map_err(|_| anyhow!("malformed duration: {:?}", s))?;
+    let d = match unit {
+        "m" => chrono::Duration::try_minutes(n),
+        "h" => chrono::Duration::try_hours(n),
+        "d" => chrono::Duration::try_days(n),
+        "w" => chrono::Duration::try_weeks(n),
+        _ => {
+            return Err(anyhow!(
+                "malformed duration: {:?} (expected a number followed by m, h, d, or w)",
+                s
+            ))
+        }
     };
+    d.ok_or_else(|| anyhow!("duration out of range: {:?}", s))
+}
 
-    Ok(ss)
+/// Parse a point in time: a duration ago (`3d`), an RFC 3339 timestamp, or a date (UTC)
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(d.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    let d = parse_duration(s).map_err(|_| {
+        anyhow!(
+            "malformed time: {:?} (expected e.g. 3d, 2021-01-31, or an RFC 3339 timestamp)",
+            s
+        )
+    })?;
+    Utc::now()
+        .checked_sub_signed(d)
+        .ok_or_else(|| anyhow!("time out of range: {:?} ago", s))
 }
 
-pub fn get_last_page(link: &str) -> usize {
+/// Parse a `Link` header into a map from `rel` to URI
+fn parse_link(link: &str) -> std::collections::HashMap<String, String> {
     use once_cell::sync::Lazy;
     use regex::Regex;
     static R_LINK: Lazy<Regex> =
         Lazy::new(|| Regex::new(r#"<(?P<uri>[^>]*)>;\srel="(?P<rel>[^"]*)"#).unwrap());
-    static R_PAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r#".*\?page=(?P<page>.*)"#).unwrap());
-
-    let last_uri = {
-        let mut map = std::collections::HashMap::new();
-        // Parse link header and retrieve rels
-        // This parses link header with the regex below:
-        // LINK ::= "<" uri ">;" WS "rel=\"" relation "\""
-        for cap in R_LINK.captures_iter(link) {
-            map.insert(cap["rel"].to_owned(), cap["uri"].to_owned());
-        }
-        map.remove("last").unwrap()
+
+    // This parses link header with the regex below:
+    // LINK ::= "<" uri ">;" WS "rel=\"" relation "\""
+    R_LINK
+        .captures_iter(link)
+        .map(|cap| (cap["rel"].to_owned(), cap["uri"].to_owned()))
+        .collect()
+}
+
+pub fn get_last_page(link: &str) -> usize {
+    use once_cell::sync::Lazy;
+    use regex::Regex;
+    static R_PAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r".*\?page=(?P<page>.*)").unwrap());
+
+    // a single page has no `rel="last"`
+    let Some(last_uri) = parse_link(link).remove("last") else {
+        return 1;
     };
 
     R_PAGE.captures(&last_uri).unwrap()["page"].parse().unwrap()
 }
+
+/// URI of the next page from a `Link` header, if any
+pub fn get_next_page(link: &str) -> Option<String> {
+    parse_link(link).remove("next")
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use clap::ArgMatches;
+use tokio::time::{sleep, Duration};
+
+use github_notification_filter::journal::{Action, Journal};
+use github_notification_filter::util::{self, Filters, StateFilter};
+use github_notification_filter::{snooze, Client, Subscription, ThreadID};
+
+/// Used when GitHub doesn't send `X-Poll-Interval`
+const DEFAULT_INTERVAL: u64 = 60;
+
+/// stdout is closed, which ends watch instead of being retried like failed polls
+#[derive(Debug, thiserror::Error)]
+#[error("failed to write to stdout")]
+struct OutputError(#[source] std::io::Error);
+
+/// Print the result of an action, with the time as watch runs for long
+fn report(msg: impl std::fmt::Display) -> Result<(), OutputError> {
+    outln!(
+        "{} {}",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        msg
+    )
+    .map_err(OutputError)
+}
+
+struct Watcher<'a> {
+    filt: Filters,
+    action: Action,
+    /// `None` with `--dry-run`, which doesn't touch the journal either
+    journal: Option<Journal>,
+    c: &'a Client,
+    /// `updated_at` of the threads in the last poll
+    seen: HashMap<ThreadID, String>,
+    last_modified: Option<String>,
+    /// snoozed threads already reported as due
+    due: HashSet<ThreadID>,
+}
+
+impl Watcher<'_> {
+    /// Poll once and handle new threads, returns `X-Poll-Interval`
+    async fn tick(&mut self) -> Result<Option<u64>> {
+        match self.filt.reload_rules() {
+            Ok(true) => tracing::info!("Reloaded ~/.ghnf/filters"),
+            Ok(false) => {}
+            Err(e) => tracing::warn!(
+                error = %format!("{:#}", e),
+                "Failed to reload ~/.ghnf/filters, keeping the previous rules"
+            ),
+        }
+
+        match snooze::load() {
+            Ok(snoozed) => {
+                let now = chrono::Utc::now();
+                for e in snoozed.iter().filter(|e| e.is_due(now)) {
+                    if self.due.insert(e.thread_id) {
+                        tracing::info!(thread_id = e.thread_id, "Snoozed thread due: {}", e);
+                    }
+                }
+            }
+            Err(e) => tracing::warn!(error = %format!("{:#}", e), "Failed to read ~/.ghnf/snoozed"),
+        }
+
+        let polled = Subscription::poll_unread(self.c, self.last_modified.as_deref()).await?;
+        if let Some(ss) = polled.subscriptions {
+            let new: Vec<Subscription> = ss
+                .iter()
+                .filter(|s| self.seen.get(&s.thread_id) != Some(&s.updated_at))
+                .cloned()
+                .collect();
+            if !new.is_empty() {
+                tracing::info!(count = new.len(), "New or updated threads");
+                self.handle(new).await?;
+            }
+            self.seen = ss
+                .into_iter()
+                .map(|s| (s.thread_id, s.updated_at))
+                .collect();
+        }
+        self.last_modified = polled.last_modified;
+
+        Ok(polled.poll_interval)
+    }
+
+    async fn handle(&self, ss: Vec<Subscription>) -> Result<()> {
+        let ss = util::filter_ignored(ss)?;
+        let ss = util::apply_filters(ss, &self.filt, self.c).await?;
+        let ss =
+            util::filter_by_subject_state(ss, self.filt.state, self.filt.commits, self.c).await?;
+
+        for s in ss {
+            let rule = self.filt.matching_rule(&s).map(ToString::to_string);
+            let rule_msg = rule
+                .as_ref()
+                .map_or_else(String::new, |r| format!(" (rule: {})", r));
+            let Some(journal) = &self.journal else {
+                report(format!("Would {} {}{}", self.action, s, rule_msg))?;
+                continue;
+            };
+            let r = async {
+                if self.action == Action::Unsubscribe {
+                    s.unsubscribe(self.c).await?;
+                }
+                journal.record(&s, self.action, rule)?;
+                s.mark_as_read(self.c).await
+            };
+            match r.await {
+                Ok(()) => report(format!("{} {}{}", self.action, s, rule_msg))?,
+                Err(e) => tracing::warn!(
+                    thread_id = s.thread_id,
+                    action = %self.action,
+                    error = %format!("{:#}", e),
+                    "Failed to {} {}",
+                    self.action,
+                    s
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(m: &ArgMatches<'_>, c: &Client) -> Result<()> {
+    let min_interval = match m.value_of("interval") {
+        Some(v) => v
+            .parse()
+            .map_err(|_| anyhow!("Invalid argument for <interval>, expected integer"))?,
+        None => 0,
+    };
+
+    let mut w = Watcher {
+        filt: crate::parse_filters(m, true, StateFilter::Closed)?,
+        action: match m.value_of("action") {
+            Some("read") => Action::MarkRead,
+            _ => Action::Unsubscribe,
+        },
+        journal: if m.is_present("dry-run") {
+            None
+        } else {
+            Some(Journal::open()?)
+        },
+        c,
+        seen: HashMap::new(),
+        last_modified: None,
+        due: HashSet::new(),
+    };
+
+    // register handlers before the first poll, so that signals are never missed
+    #[cfg(unix)]
+    let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    let shutdown = async {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    tokio::pin!(shutdown);
+
+    tracing::info!(action = %w.action, "Watching notifications");
+    loop {
+        let interval = match w.tick().await {
+            Ok(i) => i,
+            Err(e) if e.is::<OutputError>() => return Err(e),
+            Err(e) => {
+                tracing::warn!(error = %format!("{:#}", e), "Failed to poll notifications");
+                None
+            }
+        }
+        .unwrap_or(DEFAULT_INTERVAL)
+        .max(min_interval);
+
+        tokio::select! {
+            () = sleep(Duration::from_secs(interval)) => {}
+            () = &mut shutdown => {
+                tracing::info!("Shutting down");
+                return Ok(());
+            }
+        }
+    }
+}
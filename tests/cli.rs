@@ -0,0 +1,553 @@
+//! Runs `ghnf` end to end against `--mock`
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use common::fixtures;
+
+/// A fresh `$HOME` with `~/.ghnf/filters`
+fn home(name: &str, filters: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("ghnf-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(home.join(".ghnf")).unwrap();
+    std::fs::write(home.join(".ghnf").join("filters"), filters).unwrap();
+    home
+}
+
+/// `tests/fixtures/mock.json` changed by `f`, saved in `home`
+fn seed(home: &Path, f: impl FnOnce(&mut serde_json::Value)) -> PathBuf {
+    let text = std::fs::read_to_string(fixtures("mock.json")).unwrap();
+    let mut seed = serde_json::from_str(&text).unwrap();
+    f(&mut seed);
+    let path = home.join("seed.json");
+    std::fs::write(&path, seed.to_string()).unwrap();
+    path
+}
+
+fn run(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ghnf"))
+        .env("HOME", home)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn ghnf(home: &Path, args: &[&str]) -> Output {
+    let mock = fixtures("mock.json");
+    let out = run(home, &[&["--mock", mock.to_str().unwrap()], args].concat());
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out
+}
+
+#[test]
+fn list_shows_the_inbox() {
+    let home = home("list", "");
+    let out = ghnf(&home, &["list"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.contains("nightly build failed again"), "{}", stdout);
+    assert!(!stdout.contains("Already read"), "{}", stdout);
+    assert!(stdout.contains("Total entry count: 5"), "{}", stdout);
+
+    let out = ghnf(&home, &["list", "--state", "closed"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.contains("Total entry count: 2"), "{}", stdout);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn recent_threads_are_kept_only_with_ghnf_dir() {
+    let home = home("recent", "");
+    ghnf(&home, &["list"]);
+    let recent = std::fs::read_to_string(home.join(".ghnf").join("recent")).unwrap();
+    assert!(recent.contains("nightly build failed again"), "{}", recent);
+
+    std::fs::remove_dir_all(home.join(".ghnf")).unwrap();
+    let out = ghnf(&home, &["list"]);
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(!stderr.contains("recent"), "{}", stderr);
+    assert!(!home.join(".ghnf").exists());
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn remove_unsubscribes_matched_threads_and_journals() {
+    let home = home("remove", "^nightly\n");
+    let out = ghnf(&home, &["remove", "--dry-run"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    // only the closed one by default
+    assert!(stdout.contains("nightly build failed ("), "{}", stdout);
+    assert!(!stdout.contains("failed again"), "{}", stdout);
+    // progress goes to the log on stderr
+    assert!(
+        stdout.starts_with("Following threads are going to be unsubscribed:\n"),
+        "{}",
+        stdout
+    );
+
+    ghnf(&home, &["remove"]);
+    let journal = std::fs::read_to_string(home.join(".ghnf").join("journal")).unwrap();
+    assert_eq!(journal.lines().count(), 1, "{}", journal);
+    assert!(journal.contains("\"thread_id\":3"), "{}", journal);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn thread_ids_go_through_the_filters() {
+    let home = home("ids", "");
+    let out = ghnf(&home, &["remove", "--dry-run", "2", "3"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    // 2 is open
+    assert!(stdout.contains("nightly build failed (3)"), "{}", stdout);
+    assert!(!stdout.contains("(2)"), "{}", stdout);
+
+    let out = ghnf(
+        &home,
+        &[
+            "remove",
+            "--dry-run",
+            "--state",
+            "any",
+            "-k",
+            "pr",
+            "1",
+            "2",
+        ],
+    );
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.contains("(1) at"), "{}", stdout);
+    assert!(!stdout.contains("(2) at"), "{}", stdout);
+
+    let mock = fixtures("mock.json");
+    let out = run(
+        &home,
+        &[
+            "--mock",
+            mock.to_str().unwrap(),
+            "open",
+            "--print-url",
+            "--author",
+            "nobody",
+            "1",
+        ],
+    );
+    assert_eq!(out.status.code(), Some(2));
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn browser_commands_are_split_like_a_shell() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = home("browser", "");
+    let dir = home.join("my browser");
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("open");
+    std::fs::write(&script, "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$0.log\"\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let browser = format!("'{}' --title 'a b' {{}}", script.display());
+    ghnf(&home, &["open", "--browser", &browser, "1"]);
+    // the browser is not waited for
+    let log = dir.join("open.log");
+    for _ in 0..50 {
+        if std::fs::read_to_string(&log).is_ok_and(|l| l.ends_with('\n')) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let args = std::fs::read_to_string(&log).unwrap();
+    let args: Vec<&str> = args.lines().collect();
+    assert_eq!(args.len(), 3, "{:?}", args);
+    assert_eq!(args[..2], ["--title", "a b"]);
+    assert!(args[2].starts_with("https://github.com/a/b/"), "{:?}", args);
+
+    let mock = fixtures("mock.json");
+    let out = run(
+        &home,
+        &[
+            "--mock",
+            mock.to_str().unwrap(),
+            "open",
+            "--browser",
+            "'unclosed",
+            "1",
+        ],
+    );
+    assert!(!out.status.success());
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn suggest_needs_filters_only_when_they_exist() {
+    let home = home("suggest", "");
+    let filters = home.join(".ghnf").join("filters");
+    std::fs::remove_file(&filters).unwrap();
+    let out = ghnf(&home, &["suggest", "--min-count", "2"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.contains("^nightly build failed"), "{}", stdout);
+    // only one of them is open
+    let out = ghnf(&home, &["suggest", "--min-count", "2", "--state", "open"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(!stdout.contains("^nightly"), "{}", stdout);
+
+    std::fs::write(&filters, "^nightly\n").unwrap();
+    let out = ghnf(&home, &["suggest", "--min-count", "2"]);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(!stdout.contains("^nightly"), "{}", stdout);
+
+    // a broken file is not taken for a missing one
+    std::fs::write(&filters, "(\n").unwrap();
+    let mock = fixtures("mock.json");
+    let out = run(&home, &["--mock", mock.to_str().unwrap(), "suggest"]);
+    assert_eq!(out.status.code(), Some(6));
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn exit_codes_tell_failures_apart() {
+    let home = home("exit", "^no such title\n");
+    let mock = fixtures("mock.json");
+    let mock = mock.to_str().unwrap();
+    let code = |args: &[&str]| run(&home, args).status.code();
+
+    assert_eq!(code(&["--mock", mock, "remove"]), Some(2));
+    assert_eq!(code(&["--mock", mock, "list", "-f", "^nightly"]), Some(0));
+
+    // no ~/.ghnf/token
+    let out = run(&home, &["list"]);
+    assert_eq!(out.status.code(), Some(6));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.starts_with("Error: ~/.ghnf/token"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+
+    let unauthorized = seed(&home, |s| s["unauthorized"] = true.into());
+    let unauthorized = unauthorized.to_str().unwrap();
+    assert_eq!(code(&["--mock", unauthorized, "list"]), Some(4));
+    assert_eq!(code(&["--mock", unauthorized, "config", "check"]), Some(4));
+
+    let exhausted = seed(&home, |s| {
+        s["rate_limit"] = serde_json::json!({ "every": 1 })
+    });
+    let exhausted = exhausted.to_str().unwrap();
+    assert_eq!(code(&["--mock", exhausted, "list"]), Some(5));
+
+    let malformed = seed(&home, |s| s["notifications"][0]["id"] = "x".into());
+    let out = run(&home, &["--mock", malformed.to_str().unwrap(), "list"]);
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("malformed thread id \"x\""), "{}", stderr);
+
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn count_tells_what_is_new_since_the_last_check() {
+    let home = home("count", "");
+    let count = |args: &[&str]| String::from_utf8(ghnf(&home, args).stdout).unwrap();
+    assert_eq!(count(&["count"]), "5 (+5)\n");
+    assert_eq!(count(&["count"]), "5\n");
+    // each filter has its own last check
+    assert_eq!(count(&["count", "--repo", "c/*"]), "2 (+2)\n");
+    assert_eq!(
+        count(&["count", "--reason", "subscribed", "-k", "pr"]),
+        "1 (+1)\n"
+    );
+    // 1 (merged) and 3
+    assert_eq!(count(&["count", "--state", "closed"]), "2 (+2)\n");
+
+    let updated = seed(&home, |s| {
+        s["notifications"][1]["updated_at"] = "2030-01-01T00:00:00Z".into();
+    });
+    let out = run(
+        &home,
+        &[
+            "--mock",
+            updated.to_str().unwrap(),
+            "count",
+            "--format",
+            "waybar",
+        ],
+    );
+    let waybar: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(waybar["text"], "5 (+1)");
+    assert_eq!(waybar["class"], "new");
+    assert!(
+        waybar["tooltip"].as_str().unwrap().contains("2  a/b"),
+        "{}",
+        waybar
+    );
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn feed_leaves_out_filtered_threads_unless_ignored() {
+    let home = home("feed", "^nightly\n");
+    std::fs::write(home.join(".ghnf").join("ignore"), "4\n").unwrap();
+    let out = home.join("inbox.xml");
+    ghnf(&home, &["feed", "-o", out.to_str().unwrap()]);
+
+    let xml = std::fs::read_to_string(&out).unwrap();
+    assert_eq!(xml.matches("<entry>").count(), 4, "{}", xml);
+    assert!(
+        !xml.contains("<title>nightly build failed</title>"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<title>nightly build failed again</title>"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains(r#"<link href="https://github.com/a/b/pull/1"/>"#),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<id>https://api.github.com/notifications/threads/2</id>"),
+        "{}",
+        xml
+    );
+
+    let out = ghnf(&home, &["feed", "--all", "-k", "pr"]);
+    let xml = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(xml.matches("<entry>").count(), 1, "{}", xml);
+
+    // everything without filters, but a broken file is an error
+    let filters = home.join(".ghnf").join("filters");
+    std::fs::remove_file(&filters).unwrap();
+    let out = ghnf(&home, &["feed"]);
+    let xml = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(xml.matches("<entry>").count(), 5, "{}", xml);
+    std::fs::write(&filters, "(\n").unwrap();
+    let mock = fixtures("mock.json");
+    let out = run(&home, &["--mock", mock.to_str().unwrap(), "feed"]);
+    assert_eq!(out.status.code(), Some(6));
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn digest_groups_the_window_and_lists_auto_unsubscribed() {
+    let home = home("digest", "");
+    let entry = |id: u64, title: &str, action: &str, rule: &str| {
+        format!(
+            r#"{{"timestamp":"2021-01-04T12:00:00Z","run":"2021-01-04T12:00:00Z","thread_id":{},"repo":"x/y","title":"{}","action":"{}","rule":{}}}"#,
+            id, title, action, rule
+        )
+    };
+    std::fs::write(
+        home.join(".ghnf").join("journal"),
+        [
+            entry(7, "Bump *", "unsubscribe", r#""^bump""#),
+            entry(8, "by hand", "unsubscribe", "null"),
+            entry(9, "undone", "unsubscribe", r#""^undone""#),
+            entry(9, "undone", "resubscribe", "null"),
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+
+    let out = ghnf(&home, &["digest", "--since", "2021-01-04"]);
+    let md = String::from_utf8(out.stdout).unwrap();
+    assert!(
+        md.contains("2 updated thread(s) in 2 repository(ies)"),
+        "{}",
+        md
+    );
+    assert!(md.contains("## c/d (1)\n\n### subscribed (1)\n"), "{}", md);
+    assert!(
+        md.contains(
+            "- 🟢 open [nightly build failed again](https://github.com/c/d/issues/4) (issue)"
+        ),
+        "{}",
+        md
+    );
+    assert!(
+        md.contains("## Auto-unsubscribed (1)\n\n- x/y: Bump \\* (rule: `^bump`)"),
+        "{}",
+        md
+    );
+    assert!(!md.contains("by hand") && !md.contains("undone"), "{}", md);
+
+    let out = ghnf(
+        &home,
+        &["digest", "--since", "2021-01-01", "--format", "html"],
+    );
+    let html = String::from_utf8(out.stdout).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"), "{}", html);
+    assert!(html.contains("<h2>a/b (2)</h2>"), "{}", html);
+    assert!(
+        html.contains("🟣 merged <a href=\"https://github.com/a/b/pull/1\">"),
+        "{}",
+        html
+    );
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+/// Names in the SUBCOMMANDS section of `--help`, without `help`
+fn subcommands(home: &Path, path: &[&str]) -> Vec<String> {
+    let out = run(home, &[path, &["--help"]].concat());
+    let help = String::from_utf8(out.stdout).unwrap();
+    help.lines()
+        .skip_while(|l| *l != "SUBCOMMANDS:")
+        .skip(1)
+        // descriptions are wrapped deeper
+        .filter(|l| l.starts_with("    ") && !l.starts_with("     "))
+        .map(|l| l.split_whitespace().next().unwrap().to_owned())
+        .filter(|name| name != "help")
+        .collect()
+}
+
+#[test]
+fn man_page_covers_every_subcommand() {
+    let home = home("man", "");
+    let mut expected = vec![];
+    for name in subcommands(&home, &[]) {
+        for sub in subcommands(&home, &[&name]) {
+            expected.push(format!("{} {}", name, sub));
+        }
+        expected.push(name);
+    }
+    expected.sort();
+
+    let man = String::from_utf8(ghnf(&home, &["man"]).stdout).unwrap();
+    let mut sections: Vec<String> = man
+        .lines()
+        .filter_map(|l| l.strip_prefix(".SS ghnf "))
+        .map(ToOwned::to_owned)
+        .collect();
+    sections.sort();
+    assert_eq!(sections, expected);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn zsh_completes_thread_ids_and_repositories() {
+    let home = home("zsh", "");
+    let script = String::from_utf8(ghnf(&home, &["completions", "zsh"]).stdout).unwrap();
+    assert!(script.contains("\n_ghnf_thread_ids() {"), "{}", script);
+    assert!(script.contains("\n_ghnf_repos() {"), "{}", script);
+
+    let lines: Vec<&str> = script.lines().collect();
+    // remove (twice, for `rm`), plan, open, show and snooze
+    let ids = lines
+        .iter()
+        .filter(|l| l.ends_with(":_ghnf_thread_ids' \\"))
+        .count();
+    assert_eq!(ids, 6, "{}", script);
+    assert!(!lines
+        .iter()
+        .any(|l| l.contains("thread_id") && l.contains("_files")));
+    // read and count
+    let repos: Vec<&&str> = lines
+        .iter()
+        .filter(|l| l.starts_with("'--repo=["))
+        .collect();
+    assert_eq!(repos.len(), 2, "{:?}", repos);
+    assert!(
+        repos.iter().all(|l| l.ends_with("]: :_ghnf_repos' \\")),
+        "{:?}",
+        repos
+    );
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn closed_stdout_is_not_an_error() {
+    use std::process::Stdio;
+
+    let home = home("epipe", "");
+    let mock = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mock.json");
+    for args in [
+        &["man"][..],
+        &["completions", "zsh"],
+        &["--mock", mock, "list", "-q"],
+    ] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ghnf"))
+            .env("HOME", &home)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // like `| head` exiting before ghnf writes
+        drop(child.stdout.take());
+        let out = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8(out.stderr).unwrap();
+        assert!(out.status.success(), "{:?}: {}", args, stderr);
+        assert!(stderr.is_empty(), "{:?}: {}", args, stderr);
+    }
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn repos_unwatch_goes_on_after_a_failure() {
+    let home = home("unwatch", "");
+    // 5 requests to list, so that unwatching a/b is the one rate limited
+    let seed = seed(&home, |s| {
+        s["rate_limit"] = serde_json::json!({ "every": 6 })
+    });
+    let out = run(
+        &home,
+        &["--mock", seed.to_str().unwrap(), "repos", "unwatch", "*"],
+    );
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert_eq!(out.status.code(), Some(3), "{}", stderr);
+    assert_eq!(stdout, "unwatched c/d\n");
+    assert!(stderr.contains("Failed to unwatch a/b"), "{}", stderr);
+
+    let journal = std::fs::read_to_string(home.join(".ghnf").join("journal")).unwrap();
+    assert_eq!(journal.lines().count(), 1, "{}", journal);
+    assert!(journal.contains("\"repo\":\"c/d\""), "{}", journal);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_prints_only_actions_on_stdout() {
+    use std::process::Stdio;
+
+    let home = home("watch", "^nightly\n");
+    let out = home.join("out");
+    let mock = fixtures("mock.json");
+    let child = Command::new(env!("CARGO_BIN_EXE_ghnf"))
+        .env("HOME", &home)
+        .args(["-q", "--mock", mock.to_str().unwrap(), "watch", "--dry-run"])
+        .stdout(std::fs::File::create(&out).unwrap())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    for _ in 0..50 {
+        if std::fs::read_to_string(&out).is_ok_and(|o| o.ends_with('\n')) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    // -q leaves only warnings
+    assert!(stderr.is_empty(), "{}", stderr);
+
+    let stdout = std::fs::read_to_string(&out).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(
+        lines[0].ends_with("Would unsubscribe [Issue] c/d : nightly build failed (3) at 2021-01-03T00:00:00Z (rule: ~/.ghnf/filters:1 ^nightly)"),
+        "{}",
+        stdout
+    );
+    std::fs::remove_dir_all(&home).unwrap();
+}
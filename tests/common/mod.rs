@@ -0,0 +1,35 @@
+// each test binary uses only a part of these
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use github_notification_filter::transport::mock::{MockTransport, RateLimit, Seed};
+use github_notification_filter::transport::ReplayTransport;
+use github_notification_filter::{Client, Subscription};
+
+pub fn fixtures(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// A client replaying `tests/fixtures/<name>`, and the transport to check what was served
+pub fn replay(name: &str) -> (Arc<ReplayTransport>, Client) {
+    let t = Arc::new(ReplayTransport::open(&fixtures(name)).unwrap());
+    (t.clone(), Client::new(t))
+}
+
+pub fn ids(ss: &[Subscription]) -> Vec<u64> {
+    ss.iter().map(|s| s.thread_id).collect()
+}
+
+/// A client faking GitHub in memory, seeded from `tests/fixtures/mock.json`
+pub fn mock(rate_limit: Option<RateLimit>) -> (Arc<MockTransport>, Client) {
+    let text = std::fs::read_to_string(fixtures("mock.json")).unwrap();
+    let mut seed: Seed = serde_json::from_str(&text).unwrap();
+    seed.rate_limit = rate_limit;
+    let t = Arc::new(MockTransport::new(seed).unwrap());
+    (t.clone(), Client::new(t))
+}
@@ -0,0 +1,80 @@
+//! What `ghnf undo` picks from `~/.ghnf/journal`
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use github_notification_filter::journal::{self, Action, Entry};
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2021, 1, 1, hour, 0, 0).unwrap()
+}
+
+/// An entry on thread `id` (or the repository with `None`) written by the run started at `run`
+fn entry(run: u32, id: Option<u64>, action: Action) -> Entry {
+    Entry {
+        timestamp: at(run),
+        run: at(run),
+        thread_id: id,
+        repo: "a/b".to_owned(),
+        title: "t".to_owned(),
+        action,
+        rule: None,
+    }
+}
+
+fn undone(entries: &[Entry], since: Option<DateTime<Utc>>) -> Vec<(u32, Option<u64>)> {
+    use chrono::Timelike;
+    journal::undoable(entries, since)
+        .into_iter()
+        .map(|e| (e.run.hour(), e.thread_id))
+        .collect()
+}
+
+#[test]
+fn undo_picks_the_last_run() {
+    let entries = [
+        entry(1, Some(1), Action::Unsubscribe),
+        entry(2, Some(2), Action::Unsubscribe),
+        entry(2, None, Action::UnwatchRepo),
+        // not undoable, so it doesn't make a run
+        entry(3, Some(3), Action::MarkRead),
+    ];
+    assert_eq!(undone(&entries, None), [(2, Some(2)), (2, None)]);
+    assert_eq!(
+        undone(&entries, Some(at(1))),
+        [(1, Some(1)), (2, Some(2)), (2, None)]
+    );
+}
+
+#[test]
+fn undo_redo_undo() {
+    let mut entries = vec![
+        entry(1, Some(1), Action::Unsubscribe),
+        entry(1, Some(2), Action::Unsubscribe),
+    ];
+    assert_eq!(undone(&entries, None), [(1, Some(1)), (1, Some(2))]);
+
+    // undo
+    entries.push(entry(2, Some(2), Action::Resubscribe));
+    entries.push(entry(2, Some(1), Action::Resubscribe));
+    assert_eq!(undone(&entries, None), []);
+
+    // redo only the first thread, which is the only one to undo again
+    entries.push(entry(3, Some(1), Action::Unsubscribe));
+    assert_eq!(undone(&entries, None), [(3, Some(1))]);
+    assert_eq!(undone(&entries, Some(at(0))), [(3, Some(1))]);
+
+    // undo again
+    entries.push(entry(4, Some(1), Action::Resubscribe));
+    assert_eq!(undone(&entries, Some(at(0))), []);
+}
+
+#[test]
+fn repeated_actions_are_undone_once() {
+    let entries = [
+        entry(1, Some(1), Action::Unsubscribe),
+        entry(2, Some(1), Action::Unsubscribe),
+        entry(2, None, Action::IgnoreRepo),
+        entry(3, None, Action::IgnoreRepo),
+    ];
+    assert_eq!(undone(&entries, Some(at(0))), [(2, Some(1)), (3, None)]);
+}
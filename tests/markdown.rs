@@ -0,0 +1,71 @@
+//! How `ghnf show` renders issue and pull request bodies
+
+use github_notification_filter::markdown::render;
+
+#[test]
+fn headings() {
+    assert_eq!(render("# Title\n\nbody", false), "Title\n\nbody");
+    assert_eq!(
+        render("## Title\nbody", true),
+        "\x1b[1m\x1b[4mTitle\x1b[0m\n\nbody"
+    );
+}
+
+#[test]
+fn code() {
+    assert_eq!(render("run `cargo test`", false), "run `cargo test`");
+    assert_eq!(
+        render("run `cargo test`", true),
+        "run \x1b[36mcargo test\x1b[0m"
+    );
+    assert_eq!(
+        render(
+            "before\n\n```rust\nfn main() {}\n\nlet x;\n```\nafter",
+            false
+        ),
+        "before\n\n    fn main() {}\n    \n    let x;\n\nafter"
+    );
+}
+
+#[test]
+fn lists() {
+    assert_eq!(render("- a\n- b", false), "• a\n• b");
+    assert_eq!(render("3. a\n1. b", false), "3. a\n4. b");
+    assert_eq!(
+        render("- a\n  1. b\n  2. c\n- d", false),
+        "• a\n  1. b\n  2. c\n• d"
+    );
+    assert_eq!(
+        render("- [x] done\n- [ ] todo\n\nafter", false),
+        "• [x] done\n• [ ] todo\n\nafter"
+    );
+}
+
+#[test]
+fn links() {
+    assert_eq!(
+        render("see [the docs](https://docs.rs)", false),
+        "see the docs <https://docs.rs>"
+    );
+    assert_eq!(
+        render("see [the docs](https://docs.rs)", true),
+        "see \x1b[4mthe docs\x1b[0m <https://docs.rs>"
+    );
+    // autolinks are not repeated
+    assert_eq!(
+        render("see <https://docs.rs>", false),
+        "see https://docs.rs"
+    );
+    assert_eq!(
+        render("![logo](https://x/y.png)", false),
+        "[image: logo <https://x/y.png>]"
+    );
+}
+
+#[test]
+fn quotes_and_comments() {
+    assert_eq!(
+        render("<!-- template -->\n> quoted\n> twice\n\nreply", false),
+        "│ quoted\n│ twice\n\nreply"
+    );
+}
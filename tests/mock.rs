@@ -0,0 +1,159 @@
+mod common;
+
+use github_notification_filter::transport::mock::RateLimit;
+use github_notification_filter::{
+    repos, util, Filters, GithubNotifications, SubjectType, Subscription,
+};
+
+use common::{ids, mock};
+
+#[tokio::test]
+async fn fetch_follows_pagination() {
+    let (t, c) = mock(None);
+    let gh = GithubNotifications::from_client(c);
+    let mut ss = gh.fetch_unread().await.unwrap();
+    ss.sort_by_key(|s| s.thread_id);
+    // 6 is read; 5 unread threads in 3 pages of 2, plus the HEAD request
+    assert_eq!(ids(&ss), [1, 2, 3, 4, 5]);
+    assert_eq!(t.requests(), 4);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried() {
+    let limit = RateLimit {
+        every: 2,
+        retry_after: Some(0),
+    };
+    let (t, c) = mock(Some(limit));
+    let gh = GithubNotifications::from_client(c);
+    let filt = Filters {
+        kind: Some(SubjectType::Issue),
+        ..Filters::default()
+    };
+    let mut ss = gh.fetch(&filt).await.unwrap();
+    ss.sort_by_key(|s| s.thread_id);
+    assert_eq!(ids(&ss), [2, 3, 4, 5]);
+
+    let n = ss.iter().find(|s| s.thread_id == 5).unwrap();
+    gh.unsubscribe(n).await.unwrap();
+    assert!(!t.thread("5").unwrap().subscribed);
+    // 5 requests went through, each after one answered with 403 (but the first)
+    assert_eq!(t.requests(), 9);
+}
+
+#[tokio::test]
+async fn unsubscribed_and_read_threads_leave_the_inbox() {
+    let (t, c) = mock(None);
+    let gh = GithubNotifications::from_client(c.clone());
+    let n: Subscription = gh.thread(3).await.unwrap();
+    assert!(t.thread("3").unwrap().unread);
+
+    gh.unsubscribe(&n).await.unwrap();
+    gh.mark_as_read(&n).await.unwrap();
+    let t3 = t.thread("3").unwrap();
+    assert!(!t3.subscribed && !t3.unread);
+
+    let mut ss = gh.fetch_unread().await.unwrap();
+    ss.sort_by_key(|s| s.thread_id);
+    assert_eq!(ids(&ss), [1, 2, 4, 5]);
+
+    // undo resubscribes
+    Subscription::resubscribe(3, &c).await.unwrap();
+    assert!(t.thread("3").unwrap().subscribed);
+}
+
+#[tokio::test]
+async fn subject_state_is_read_from_the_detail() {
+    let (_, c) = mock(None);
+    let ss = util::fetch_filtered(&Filters::default(), &c).await.unwrap();
+    let ss = util::filter_by_subject_state(
+        ss,
+        github_notification_filter::StateFilter::Closed,
+        github_notification_filter::CommitPolicy::Exclude,
+        &c,
+    )
+    .await
+    .unwrap();
+    let mut ids = ids(&ss);
+    ids.sort_unstable();
+    assert_eq!(ids, [1, 3]);
+}
+
+#[tokio::test]
+async fn bulk_read_marks_threads_until_the_time() {
+    let (t, c) = mock(None);
+    let before = "2021-01-04T00:00:00Z";
+    let ss = Subscription::fetch_unread_before(Some("c/d"), before, &c)
+        .await
+        .unwrap();
+    assert_eq!(ids(&ss), [3]);
+
+    assert!(Subscription::mark_all_as_read(Some("c/d"), before, &c)
+        .await
+        .unwrap());
+    assert!(!t.thread("3").unwrap().unread);
+    assert!(t.thread("4").unwrap().unread);
+
+    assert!(
+        Subscription::mark_all_as_read(None, "2030-01-01T00:00:00Z", &c)
+            .await
+            .unwrap()
+    );
+    assert!(
+        Subscription::fetch_unread_before(None, "2030-01-01T00:00:00Z", &c)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn repositories_can_be_unwatched_and_ignored() {
+    let (t, c) = mock(None);
+    assert_eq!(repos::fetch_watched(&c).await.unwrap(), ["a/b", "c/d"]);
+
+    repos::unwatch("a/b", &c).await.unwrap();
+    repos::ignore("e/f", &c).await.unwrap();
+    assert_eq!(t.watched(), ["c/d"]);
+    assert_eq!(t.ignored_repos(), ["e/f"]);
+
+    repos::unignore("e/f", &c).await.unwrap();
+    repos::watch("a/b", &c).await.unwrap();
+    assert_eq!(t.watched(), ["c/d", "a/b"]);
+    assert!(t.ignored_repos().is_empty());
+}
+
+#[tokio::test]
+async fn threads_marked_as_done_are_gone() {
+    let (t, c) = mock(None);
+    let url = "https://api.github.com/notifications/threads/3";
+    let resp = c.delete(url).send().await.unwrap();
+    assert_eq!(resp.status(), 204);
+    assert!(t.thread("3").is_none());
+    assert_eq!(c.get(url).send().await.unwrap().status(), 404);
+    assert_eq!(c.delete(url).send().await.unwrap().status(), 404);
+
+    let gh = GithubNotifications::from_client(c);
+    let mut ss = gh.fetch_unread().await.unwrap();
+    ss.sort_by_key(|s| s.thread_id);
+    assert_eq!(ids(&ss), [1, 2, 4, 5]);
+}
+
+#[tokio::test]
+async fn link_lists_parameters_in_order() {
+    let (_, c) = mock(None);
+    let resp = c
+        .get("https://api.github.com/notifications")
+        .query(&[("z", "1"), ("all", "false"), ("page", "2"), ("m", "x")])
+        .send()
+        .await
+        .unwrap();
+    let link = resp.headers()["link"].to_str().unwrap();
+    assert!(
+        link.starts_with(
+            "<https://api.github.com/notifications?all=false&m=x&z=1&page=3>; rel=\"next\""
+        ),
+        "{}",
+        link
+    );
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use github_notification_filter::rules::{Rule, RuleSet};
+use github_notification_filter::transport::{RecordTransport, ReplayTransport};
+use github_notification_filter::{util, Client, CommitPolicy, Filters, StateFilter, SubjectType};
+
+use common::{ids, replay};
+
+fn rules(sources: &[&str]) -> Option<RuleSet> {
+    Some(RuleSet {
+        rules: sources
+            .iter()
+            .zip(1..)
+            .map(|(s, i)| Rule::parse(s, "test", i).unwrap())
+            .collect(),
+        files: vec![],
+    })
+}
+
+#[tokio::test]
+async fn fetch_filtered_reads_every_page() {
+    let (t, c) = replay("inbox");
+    let ss = util::fetch_filtered(&Filters::default(), &c).await.unwrap();
+    assert_eq!(ids(&ss), [1, 2, 3, 4]);
+    // the subject detail is not needed without detail filters
+    assert_eq!(t.unused().len(), 4);
+}
+
+#[tokio::test]
+async fn fetch_filtered_applies_rules_and_kind() {
+    let (_, c) = replay("inbox");
+    let filt = Filters {
+        rules: rules(&["^bump"]),
+        ..Filters::default()
+    };
+    assert_eq!(ids(&util::fetch_filtered(&filt, &c).await.unwrap()), [1, 3]);
+
+    let filt = Filters {
+        kind: Some(SubjectType::Issue),
+        ..Filters::default()
+    };
+    assert_eq!(ids(&util::fetch_filtered(&filt, &c).await.unwrap()), [2, 4]);
+
+    let filt = Filters {
+        rules: rules(&["type:issue", "!repo:c/*"]),
+        ..Filters::default()
+    };
+    assert_eq!(ids(&util::fetch_filtered(&filt, &c).await.unwrap()), [2]);
+}
+
+#[tokio::test]
+async fn fetch_filtered_applies_detail_filters() {
+    let (_, c) = replay("inbox");
+    let mut filt = Filters::default();
+    filt.detail.author = Some("dependabot[bot]".to_owned());
+    assert_eq!(ids(&util::fetch_filtered(&filt, &c).await.unwrap()), [1]);
+
+    let mut filt = Filters::default();
+    filt.detail.not_planned = true;
+    assert_eq!(ids(&util::fetch_filtered(&filt, &c).await.unwrap()), [4]);
+}
+
+#[tokio::test]
+async fn filter_by_subject_state() {
+    let (_, c) = replay("inbox");
+    let all = util::fetch_filtered(&Filters::default(), &c).await.unwrap();
+
+    let cases = [
+        (StateFilter::Closed, CommitPolicy::Include, vec![1, 3, 4]),
+        (StateFilter::Closed, CommitPolicy::Exclude, vec![1, 4]),
+        (StateFilter::Open, CommitPolicy::Exclude, vec![2]),
+        (StateFilter::Merged, CommitPolicy::Exclude, vec![1]),
+        (StateFilter::ClosedUnmerged, CommitPolicy::Exclude, vec![4]),
+        (StateFilter::Any, CommitPolicy::Include, vec![1, 2, 3, 4]),
+        (StateFilter::Any, CommitPolicy::Exclude, vec![1, 2, 4]),
+    ];
+    for (state, commits, expected) in cases {
+        let ss = util::filter_by_subject_state(all.clone(), state, commits, &c)
+            .await
+            .unwrap();
+        assert_eq!(ids(&ss), expected, "{} {:?}", state, commits);
+    }
+}
+
+#[tokio::test]
+async fn recorded_fixtures_replay() {
+    let dir = std::env::temp_dir().join(format!("ghnf-record-{}", std::process::id()));
+    let (inner, _) = replay("inbox");
+    let recording = Client::new(RecordTransport::new(inner, &dir).unwrap());
+    let recorded = util::fetch_filtered(&Filters::default(), &recording)
+        .await
+        .unwrap();
+
+    let replaying = Client::new(ReplayTransport::open(&dir).unwrap());
+    let replayed = util::fetch_filtered(&Filters::default(), &replaying)
+        .await
+        .unwrap();
+    assert_eq!(ids(&recorded), ids(&replayed));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn replay_fails_without_fixture() {
+    let (_, c) = replay("inbox");
+    let err = c
+        .get("https://api.github.com/notifications/threads/42")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("No fixture"), "{}", err);
+}
@@ -0,0 +1,198 @@
+//! The grammar of `~/.ghnf/filters`
+
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+use github_notification_filter::rules::{self, Rule, RuleSet};
+use github_notification_filter::subscription::gh_objects;
+use github_notification_filter::Subscription;
+
+fn thread(repo: &str, kind: &str, title: &str) -> Subscription {
+    let n: gh_objects::Notification = serde_json::from_value(serde_json::json!({
+        "id": "1",
+        "repository": { "full_name": repo },
+        "subject": { "title": title, "url": null, "type": kind, "latest_comment_url": null },
+        "updated_at": "2021-01-01T00:00:00Z",
+        "reason": "subscribed",
+        "last_read_at": null,
+    }))
+    .unwrap();
+    Subscription::try_from(n).unwrap()
+}
+
+fn rule_set(sources: &[&str]) -> RuleSet {
+    RuleSet {
+        rules: sources
+            .iter()
+            .zip(1..)
+            .map(|(s, i)| Rule::parse(s, "test", i).unwrap())
+            .collect(),
+        files: vec![],
+    }
+}
+
+/// A fresh directory with `files` (name, content)
+fn dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ghnf-rules-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (f, content) in files {
+        let path = dir.join(f);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn sources(rules: &[Rule]) -> Vec<&str> {
+    rules.iter().map(|r| r.source.as_str()).collect()
+}
+
+fn messages(problems: &[github_notification_filter::config::Problem]) -> Vec<&str> {
+    problems.iter().map(|p| p.message.as_str()).collect()
+}
+
+#[test]
+fn negation_overrides_matches() {
+    let r = rule_set(&["^bump", "!openssl"]);
+    assert!(r.is_match(&thread("a/b", "PullRequest", "Bump serde")));
+    assert!(!r.is_match(&thread("a/b", "PullRequest", "Bump openssl")));
+    // a negation alone selects nothing
+    assert!(!rule_set(&["!openssl"]).is_match(&thread("a/b", "Issue", "serde")));
+    assert_eq!(
+        r.matching_rule(&thread("a/b", "PullRequest", "bump x"))
+            .map(|r| r.line),
+        Some(1)
+    );
+}
+
+#[test]
+fn scopes_restrict_rules() {
+    let r = rule_set(&["repo:rust-lang/* type:pr ^bump"]);
+    assert!(r.is_match(&thread("rust-lang/cargo", "PullRequest", "Bump x")));
+    assert!(r.is_match(&thread("Rust-Lang/cargo", "PullRequest", "Bump x")));
+    assert!(!r.is_match(&thread("rust-lang/cargo", "Issue", "Bump x")));
+    assert!(!r.is_match(&thread("tokio-rs/tokio", "PullRequest", "Bump x")));
+
+    // without a regex, everything in the scope
+    let r = rule_set(&["repo:a/?"]);
+    assert!(r.is_match(&thread("a/b", "Commit", "anything")));
+    assert!(!r.is_match(&thread("a/bc", "Commit", "anything")));
+
+    assert!(Rule::parse("repo:", "test", 1).is_err());
+    assert!(Rule::parse("type:bug x", "test", 1).is_err());
+}
+
+#[test]
+fn case_flags() {
+    let insensitive = rule_set(&["^wip"]);
+    assert!(insensitive.is_match(&thread("a/b", "Issue", "WIP: x")));
+
+    let sensitive = rule_set(&["case:sensitive ^WIP"]);
+    assert!(sensitive.is_match(&thread("a/b", "Issue", "WIP: x")));
+    assert!(!sensitive.is_match(&thread("a/b", "Issue", "wip: x")));
+
+    assert!(rule_set(&["case:insensitive ^WIP"]).is_match(&thread("a/b", "Issue", "wip")));
+    assert!(Rule::parse("case:upper x", "test", 1).is_err());
+    assert!(Rule::parse("case:sensitive", "test", 1).is_err());
+}
+
+#[test]
+fn comments_and_escaped_hash() {
+    let d = dir(
+        "comments",
+        &[("filters", "# a comment\n\n\\#123\n  # indented\n")],
+    );
+    let (rules, _, problems) = rules::read_rules(&d.join("filters"));
+    assert!(problems.is_empty(), "{:?}", problems);
+    assert_eq!(sources(&rules), [r"\#123"]);
+    let r = RuleSet {
+        rules,
+        files: vec![],
+    };
+    assert!(r.is_match(&thread("a/b", "Issue", "#123 is broken")));
+    assert!(!r.is_match(&thread("a/b", "Issue", "123 is broken")));
+    std::fs::remove_dir_all(&d).unwrap();
+}
+
+#[test]
+fn includes_relative_files_and_report_broken_ones() {
+    let d = dir(
+        "include",
+        &[
+            (
+                "filters",
+                "^a\n%include team/more\n%include missing\n%include\n%includeteam/more\n%include\"x\"\n",
+            ),
+            ("team/more", "^b\n%include ../filters\n%include sub\n"),
+            ("team/sub", "^c\n("),
+        ],
+    );
+    let (rules, files, problems) = rules::read_rules(&d.join("filters"));
+    assert_eq!(sources(&rules), ["^a", "^b", "^c"]);
+    assert_eq!(files.len(), 3);
+
+    let messages = messages(&problems);
+    assert_eq!(messages.len(), 6, "{:?}", messages);
+    assert!(
+        messages[0].starts_with("recursive %include of"),
+        "{:?}",
+        messages
+    );
+    assert!(messages[1].contains("regex parse error"), "{:?}", messages);
+    assert!(
+        messages[2].starts_with("included file") && messages[2].ends_with("does not exist"),
+        "{:?}",
+        messages
+    );
+    assert_eq!(messages[3], "missing file name for %include");
+    assert_eq!(problems[3].line, Some(4));
+    // not read as includes of `team/more` and `"x"`
+    assert_eq!(messages[4], "unknown directive %includeteam/more");
+    assert_eq!(messages[5], "unknown directive %include\"x\"");
+    assert_eq!(
+        problems[5].to_string(),
+        format!(
+            "{}:6: error: unknown directive %include\"x\"",
+            problems[5].file
+        )
+    );
+    std::fs::remove_dir_all(&d).unwrap();
+}
+
+/// The only test touching `$HOME`, as tests run in parallel
+#[test]
+fn home_includes_and_duplicates() {
+    let home = dir(
+        "home",
+        &[
+            (
+                ".ghnf/filters",
+                "%include ~/shared\nFoo\nfoo\ncase:sensitive Foo\ncase:sensitive foo\n",
+            ),
+            ("shared", "type:pr repo:a/* x\nrepo:a/* type:pr X\n"),
+        ],
+    );
+    std::env::set_var("HOME", &home);
+
+    let (rules, problems) = rules::check_rules().unwrap();
+    assert_eq!(rules.len(), 6);
+    let dups: Vec<(&str, Option<usize>)> =
+        problems.iter().map(|p| (p.file.as_str(), p.line)).collect();
+    // `Foo`/`foo` are the same insensitive pattern, but different sensitive ones
+    assert_eq!(dups, [("~/shared", Some(2)), ("~/.ghnf/filters", Some(3))]);
+    assert!(problems[0].message.contains("~/shared:1"), "{:?}", problems);
+
+    assert_eq!(rules::load_rules().unwrap().rules.len(), 6);
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn missing_file_is_a_problem() {
+    let (rules, _, problems) = rules::read_rules(Path::new("/nonexistent/ghnf/filters"));
+    assert!(rules.is_empty());
+    assert!(
+        messages(&problems)[0].starts_with("Failed to read"),
+        "{:?}",
+        problems
+    );
+}
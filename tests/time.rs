@@ -0,0 +1,70 @@
+//! Durations and points in time given on the command line (`--since`, `snooze`)
+
+use chrono::{Duration, TimeZone, Utc};
+
+use github_notification_filter::snooze;
+use github_notification_filter::util::{parse_duration, parse_time};
+
+#[test]
+fn durations() {
+    assert_eq!(parse_duration("30m").unwrap(), Duration::minutes(30));
+    assert_eq!(parse_duration("12h").unwrap(), Duration::hours(12));
+    assert_eq!(parse_duration("3d").unwrap(), Duration::days(3));
+    assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+    assert_eq!(parse_duration("0d").unwrap(), Duration::zero());
+
+    for s in ["", "d", "3", "3s", "3 d", "-3d", "3dd", "1.5h"] {
+        let e = parse_duration(s).unwrap_err().to_string();
+        assert!(e.starts_with("malformed duration"), "{:?}: {}", s, e);
+    }
+    // too large for an i64
+    assert!(parse_duration("99999999999999999999d").is_err());
+}
+
+#[test]
+fn durations_out_of_range() {
+    let e = parse_duration("9223372036854775807w")
+        .unwrap_err()
+        .to_string();
+    assert!(e.starts_with("duration out of range"), "{}", e);
+    assert!(parse_duration("9223372036854775807d").is_err());
+    assert!(parse_duration("9223372036854775807m").is_err());
+}
+
+#[test]
+fn times() {
+    assert_eq!(
+        parse_time("2021-01-31").unwrap(),
+        Utc.with_ymd_and_hms(2021, 1, 31, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        parse_time("2021-01-31T12:00:00+09:00").unwrap(),
+        Utc.with_ymd_and_hms(2021, 1, 31, 3, 0, 0).unwrap()
+    );
+
+    let before = Utc::now();
+    let t = parse_time("1d").unwrap();
+    assert!(t <= before - Duration::days(1) + Duration::seconds(1));
+    assert!(t >= before - Duration::days(1) - Duration::seconds(1));
+
+    for s in ["", "yesterday", "2021-13-01", "3s"] {
+        let e = parse_time(s).unwrap_err().to_string();
+        assert!(e.starts_with("malformed time"), "{:?}: {}", s, e);
+    }
+    // a valid duration, but before the earliest representable time
+    let e = parse_time("100000000w").unwrap_err().to_string();
+    assert!(e.starts_with("time out of range"), "{}", e);
+}
+
+#[test]
+fn snooze_until() {
+    let t = snooze::parse_until("2d").unwrap();
+    assert!(t > Utc::now() + Duration::days(2) - Duration::seconds(1));
+    assert_eq!(
+        snooze::parse_until("2021-01-31").unwrap(),
+        Utc.with_ymd_and_hms(2021, 1, 31, 0, 0, 0).unwrap()
+    );
+    let e = snooze::parse_until("100000000w").unwrap_err().to_string();
+    assert!(e.starts_with("time out of range"), "{}", e);
+    assert!(snooze::parse_until("").is_err());
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use github_notification_filter::journal::{self, Action};
+use github_notification_filter::rules::{Rule, RuleSet};
+use github_notification_filter::{util, Filters};
+
+use common::{ids, replay};
+
+#[tokio::test]
+async fn unsubscribe_all_unsubscribes_marks_read_and_journals() {
+    // the journal is written under $HOME, this is the only test in this binary touching it
+    let home = std::env::temp_dir().join(format!("ghnf-home-{}", std::process::id()));
+    std::fs::create_dir_all(home.join(".ghnf")).unwrap();
+    std::env::set_var("HOME", &home);
+
+    let (t, c) = replay("unsubscribe");
+    let filt = Filters {
+        rules: Some(RuleSet {
+            rules: vec![Rule::parse("^nightly", "test", 1).unwrap()],
+            files: vec![],
+        }),
+        ..Filters::default()
+    };
+    let ss = util::fetch_filtered(&filt, &c).await.unwrap();
+    assert_eq!(ids(&ss), [4]);
+
+    util::unsubscribe_all(ss, &filt, &c).await.unwrap();
+    // DELETE on the subscription and PATCH on the thread
+    assert!(t.unused().is_empty(), "not requested: {:?}", t.unused());
+
+    let entries = journal::read_all().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].thread_id, Some(4));
+    assert_eq!(entries[0].action, Action::Unsubscribe);
+    assert_eq!(entries[0].rule.as_deref(), Some("test:1 ^nightly"));
+
+    std::fs::remove_dir_all(&home).unwrap();
+}